@@ -0,0 +1,145 @@
+use std::io::{self, ErrorKind};
+
+use crate::{
+    bson::{oid::ObjectId, Document},
+    data_encoder::DataEncoder,
+    file_center::chunk_document,
+    tokio::io::{AsyncRead, AsyncReadExt},
+    Digest, Hasher,
+};
+
+/// The default maximum number of chunks in a batch.
+pub const DEFAULT_CHUNK_BATCH_MAX_CHUNKS: usize = 32;
+/// The default maximum total size of the data in a batch, in bytes.
+pub const DEFAULT_CHUNK_BATCH_MAX_BYTES: usize = 8_388_608;
+
+/// How chunks are inserted into MongoDB when a file is uploaded by a path, a buffer or a reader. The chunks are collected into batches, and each batch is inserted with one `insert_many` round-trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkBatchOptions {
+    /// The maximum number of chunks in a batch. `0` is treated as `1`.
+    pub max_chunks: usize,
+    /// The maximum total size of the data in a batch, in bytes. A batch always contains at least one chunk.
+    pub max_bytes:  usize,
+    /// Whether to read the next batch from the source while the previous batch is being inserted. It doubles the memory used by an upload.
+    pub pipelined:  bool,
+}
+
+impl Default for ChunkBatchOptions {
+    #[inline]
+    fn default() -> Self {
+        ChunkBatchOptions {
+            max_chunks: DEFAULT_CHUNK_BATCH_MAX_CHUNKS,
+            max_bytes:  DEFAULT_CHUNK_BATCH_MAX_BYTES,
+            pipelined:  false,
+        }
+    }
+}
+
+/// To read a source and separate it into chunk documents.
+pub(crate) struct ChunkReader<'a, R> {
+    source:     R,
+    file_id:    ObjectId,
+    chunk_size: usize,
+    encoder:    &'a DataEncoder,
+    n:          i64,
+    file_size:  i64,
+    hasher:     Option<&'a mut Hasher>,
+}
+
+impl<'a, R: AsyncRead + Unpin> ChunkReader<'a, R> {
+    #[inline]
+    pub(crate) fn new(
+        source: R,
+        file_id: ObjectId,
+        chunk_size: usize,
+        encoder: &'a DataEncoder,
+        hasher: Option<&'a mut Hasher>,
+    ) -> ChunkReader<'a, R> {
+        ChunkReader {
+            source,
+            file_id,
+            chunk_size,
+            encoder,
+            n: 0,
+            file_size: 0,
+            hasher,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get_file_id(&self) -> ObjectId {
+        self.file_id
+    }
+
+    /// Get the size of the data which has been read.
+    #[inline]
+    pub(crate) fn get_file_size(&self) -> i64 {
+        self.file_size
+    }
+
+    async fn read_chunk(&mut self) -> Result<Option<Vec<u8>>, io::Error> {
+        let mut buffer = vec![0u8; self.chunk_size];
+
+        let mut cc = 0;
+
+        // read to full
+        loop {
+            let c = match self.source.read(&mut buffer[cc..]).await {
+                Ok(0) => break,
+                Ok(c) => c,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            cc += c;
+
+            if cc == self.chunk_size {
+                break;
+            }
+        }
+
+        // read nothing
+        if cc == 0 {
+            return Ok(None);
+        }
+
+        buffer.truncate(cc);
+
+        Ok(Some(buffer))
+    }
+
+    /// Read the chunks of the next batch. An empty batch means the source has been read to the end.
+    pub(crate) async fn read_batch(
+        &mut self,
+        options: &ChunkBatchOptions,
+    ) -> Result<Vec<Document>, io::Error> {
+        let max_chunks = options.max_chunks.max(1);
+
+        let mut batch = Vec::new();
+        let mut bytes = 0;
+
+        while batch.len() < max_chunks
+            && (batch.is_empty() || bytes + self.chunk_size <= options.max_bytes)
+        {
+            let chunk = match self.read_chunk().await? {
+                Some(chunk) => chunk,
+                None => break,
+            };
+
+            if let Some(hasher) = self.hasher.as_mut() {
+                hasher.update(&chunk);
+            }
+
+            bytes += chunk.len();
+            self.file_size += chunk.len() as i64;
+
+            let chunk = self.encoder.encode_chunk(self.file_id, self.n, chunk)?;
+
+            batch.push(chunk_document(self.file_id, self.n, chunk));
+
+            self.n += 1;
+        }
+
+        Ok(batch)
+    }
+}
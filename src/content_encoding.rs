@@ -0,0 +1,160 @@
+#[cfg(feature = "gzip")]
+use std::io::{Read, Write};
+use std::{
+    io::{self, Cursor, ErrorKind},
+    str::FromStr,
+};
+
+#[cfg(feature = "gzip")]
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+
+use crate::{
+    mime::{self, Mime},
+    tokio_stream::{self, StreamExt},
+    FileData, FileDataStream,
+};
+
+/// The encoding which the data of a file is compressed with. The names are the same as the values of the `Content-Encoding` header of HTTP.
+///
+/// The data of a file which is separated into chunks is compressed chunk by chunk, so the raw data is a sequence of concatenated gzip members or zstd frames, which is still valid for decoders.
+///
+/// Gzip needs the `gzip` feature and zstd needs the `zstd` feature. Without the feature, the data of the encoding can neither be compressed nor decompressed, but the raw data of the files stored with it can still be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    Gzip,
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Get the name of the encoding.
+    #[inline]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+
+    #[inline]
+    fn unsupported(&self) -> io::Error {
+        io::Error::new(
+            ErrorKind::Unsupported,
+            format!("the {} feature is not enabled", self.as_str()),
+        )
+    }
+
+    #[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+    pub(crate) fn encode(&self, data: &[u8]) -> Result<Vec<u8>, io::Error> {
+        match self {
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+
+                encoder.write_all(data)?;
+
+                encoder.finish()
+            },
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => zstd::stream::encode_all(data, 0),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+    pub(crate) fn decode(&self, data: &[u8]) -> Result<Vec<u8>, io::Error> {
+        match self {
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => {
+                let mut buffer = Vec::new();
+
+                MultiGzDecoder::new(data).read_to_end(&mut buffer)?;
+
+                Ok(buffer)
+            },
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => zstd::stream::decode_all(data),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    /// Decode the file data lazily. Errors are returned when the data is being read.
+    pub(crate) fn decode_file_data(self, file_data: FileData) -> FileData {
+        match file_data {
+            FileData::Buffer(data) => match self.decode(&data) {
+                Ok(data) => FileData::Buffer(data),
+                Err(err) => FileData::Stream(Box::new(tokio_stream::once(Err(err)))),
+            },
+            FileData::Stream(stream) => FileData::Stream(self.decode_stream(stream)),
+        }
+    }
+
+    /// Decode a stream of chunks chunk by chunk.
+    pub(crate) fn decode_stream(self, stream: FileDataStream) -> FileDataStream {
+        Box::new(stream.map(move |chunk| {
+            chunk.and_then(|chunk| self.decode(chunk.get_ref()).map(Cursor::new))
+        }))
+    }
+}
+
+impl FromStr for ContentEncoding {
+    type Err = ();
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(ContentEncoding::Gzip),
+            "zstd" => Ok(ContentEncoding::Zstd),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Whether the data of a file is compressed when it is being put into the file center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Always compress the data.
+    Always(ContentEncoding),
+    /// Compress the data only if its MIME type is textual, such as `text/*`, JSON, XML and JavaScript. Formats which have been compressed, such as JPEG, are stored as they are.
+    ByMimeType(ContentEncoding),
+}
+
+impl Compression {
+    /// Get the encoding for the data of a file which has the MIME type.
+    #[inline]
+    pub(crate) fn get_content_encoding(&self, mime_type: &Mime) -> Option<ContentEncoding> {
+        match self {
+            Compression::Always(encoding) => Some(*encoding),
+            Compression::ByMimeType(encoding) => {
+                if is_compressible(mime_type) {
+                    Some(*encoding)
+                } else {
+                    None
+                }
+            },
+        }
+    }
+}
+
+/// Check whether the data of a MIME type is usually worth compressing.
+pub fn is_compressible(mime_type: &Mime) -> bool {
+    if mime_type.type_() == mime::TEXT {
+        return true;
+    }
+
+    if let Some(suffix) = mime_type.suffix() {
+        if suffix == mime::JSON || suffix == mime::XML {
+            return true;
+        }
+    }
+
+    let subtype = mime_type.subtype();
+
+    mime_type.type_() == mime::APPLICATION
+        && (subtype == mime::JSON
+            || subtype == mime::XML
+            || subtype == mime::JAVASCRIPT
+            || subtype == "x-ndjson"
+            || subtype == "csv")
+}
@@ -0,0 +1,98 @@
+use std::io;
+
+use crate::{
+    bson::{oid::ObjectId, spec::BinarySubtype, Binary, Bson, Document},
+    encryption::{chunk_associated_data, file_data_associated_data},
+    ContentEncoding, EncryptionKey,
+};
+
+/// To turn the original data of a file into the data which is stored in MongoDB. The data is compressed first, and then encrypted.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DataEncoder {
+    pub(crate) content_encoding: Option<ContentEncoding>,
+    pub(crate) encryption_key:   Option<EncryptionKey>,
+}
+
+impl DataEncoder {
+    /// Encode the data of the chunk of a file whose index is `n`.
+    pub(crate) fn encode_chunk(
+        &self,
+        file_id: ObjectId,
+        n: i64,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>, io::Error> {
+        let chunk = match self.content_encoding {
+            Some(content_encoding) => content_encoding.encode(&chunk)?,
+            None => chunk,
+        };
+
+        match self.encryption_key.as_ref() {
+            Some(encryption_key) => {
+                encryption_key.encrypt(&chunk, &chunk_associated_data(file_id, n))
+            },
+            None => Ok(chunk),
+        }
+    }
+
+    /// Get the ID of the key which the data is encrypted with.
+    #[inline]
+    pub(crate) fn get_encryption_key_id(&self) -> Option<&str> {
+        self.encryption_key.as_ref().map(|encryption_key| encryption_key.get_id())
+    }
+
+    /// Put the fields which are needed to decode the chunks into the file item.
+    pub(crate) fn insert_chunk_fields(&self, file_item_raw: &mut Document) {
+        if let Some(content_encoding) = self.content_encoding {
+            file_item_raw.insert("content_encoding", content_encoding.as_str());
+        }
+
+        if let Some(encryption_key) = self.encryption_key.as_ref() {
+            file_item_raw.insert("encryption_key_id", encryption_key.get_id());
+        }
+    }
+
+    /// Put the data of a file which is not separated into chunks into the file item. The data is compressed only if it gets smaller.
+    pub(crate) fn insert_file_data(
+        &self,
+        file_item_raw: &mut Document,
+        file_id: ObjectId,
+        data: Vec<u8>,
+    ) -> Result<(), io::Error> {
+        let (data, content_encoding) = match self.content_encoding {
+            Some(content_encoding) => {
+                let encoded_data = content_encoding.encode(&data)?;
+
+                if encoded_data.len() < data.len() {
+                    (encoded_data, Some(content_encoding))
+                } else {
+                    (data, None)
+                }
+            },
+            None => (data, None),
+        };
+
+        let data = match self.encryption_key.as_ref() {
+            Some(encryption_key) => {
+                encryption_key.encrypt(&data, &file_data_associated_data(file_id))?
+            },
+            None => data,
+        };
+
+        file_item_raw.insert(
+            "file_data",
+            Bson::Binary(Binary {
+                subtype: BinarySubtype::Generic, bytes: data
+            }),
+        );
+
+        if let Some(content_encoding) = content_encoding {
+            file_item_raw.insert("content_encoding", content_encoding.as_str());
+        }
+
+        if let Some(encryption_key) = self.encryption_key.as_ref() {
+            file_item_raw.insert("encryption_key_id", encryption_key.get_id());
+        }
+
+        Ok(())
+    }
+}
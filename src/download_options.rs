@@ -0,0 +1,8 @@
+/// How the chunks of a file are retrieved from MongoDB when it is downloaded as a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DownloadOptions {
+    /// The number of chunks fetched in each round-trip of the cursor. If it is `None`, the default batch size of MongoDB is used.
+    pub batch_size: Option<u32>,
+    /// The number of chunks which are read ahead in the background while the current chunk is being consumed. `0` disables reading ahead.
+    pub read_ahead: usize,
+}
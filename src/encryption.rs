@@ -0,0 +1,228 @@
+use std::{
+    collections::HashMap,
+    io::{self, Cursor, ErrorKind},
+};
+
+#[cfg(feature = "encryption")]
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm,
+};
+#[cfg(feature = "encryption")]
+use chacha20poly1305::ChaCha20Poly1305;
+
+use crate::{bson::oid::ObjectId, tokio_stream::StreamExt, FileDataStream};
+
+#[cfg(feature = "encryption")]
+const NONCE_SIZE: usize = 12;
+
+#[cfg(not(feature = "encryption"))]
+#[inline]
+fn unsupported() -> io::Error {
+    io::Error::new(ErrorKind::Unsupported, "the encryption feature is not enabled")
+}
+
+/// The associated data of the data of a file which is not separated into chunks. It binds the ciphertext to the file, so it cannot be moved to another file without being detected.
+#[inline]
+pub(crate) fn file_data_associated_data(file_id: ObjectId) -> [u8; 12] {
+    file_id.bytes()
+}
+
+/// The associated data of a chunk. It binds the ciphertext to the file and the index of the chunk, so chunks cannot be moved to another file or reordered without being detected.
+#[inline]
+pub(crate) fn chunk_associated_data(file_id: ObjectId, n: i64) -> [u8; 20] {
+    let mut associated_data = [0u8; 20];
+
+    associated_data[..12].copy_from_slice(&file_id.bytes());
+    associated_data[12..].copy_from_slice(&n.to_be_bytes());
+
+    associated_data
+}
+
+/// The authenticated cipher which the data of files is encrypted with. The ciphers need the `encryption` feature. Without it, the data of files can neither be encrypted nor decrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncryptionAlgorithm {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+/// A 256-bit key which is identified by an ID. The ID is stored along with the encrypted files, so the key which they need can be found after the current key is rotated.
+#[derive(Educe, Clone)]
+#[educe(Debug)]
+pub struct EncryptionKey {
+    id:        String,
+    algorithm: EncryptionAlgorithm,
+    #[educe(Debug(ignore))]
+    #[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+    key:       [u8; 32],
+}
+
+impl EncryptionKey {
+    #[inline]
+    pub fn new<S: Into<String>>(id: S, algorithm: EncryptionAlgorithm, key: [u8; 32]) -> Self {
+        EncryptionKey {
+            id: id.into(),
+            algorithm,
+            key,
+        }
+    }
+
+    #[inline]
+    pub fn get_id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    #[inline]
+    pub fn get_algorithm(&self) -> EncryptionAlgorithm {
+        self.algorithm
+    }
+
+    /// Encrypt the data with a random nonce and authenticate it along with `associated_data`. The nonce is put in front of the ciphertext.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn encrypt(
+        &self,
+        data: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, io::Error> {
+        let payload = Payload {
+            msg: data, aad: associated_data
+        };
+
+        let (nonce, ciphertext) = match self.algorithm {
+            EncryptionAlgorithm::Aes256Gcm => {
+                let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+                let ciphertext =
+                    Aes256Gcm::new(&self.key.into()).encrypt(&nonce, payload).map_err(|_| {
+                        io::Error::new(ErrorKind::InvalidInput, "the data cannot be encrypted")
+                    })?;
+
+                (nonce, ciphertext)
+            },
+            EncryptionAlgorithm::ChaCha20Poly1305 => {
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+                let ciphertext =
+                    ChaCha20Poly1305::new(&self.key.into()).encrypt(&nonce, payload).map_err(
+                        |_| io::Error::new(ErrorKind::InvalidInput, "the data cannot be encrypted"),
+                    )?;
+
+                (nonce, ciphertext)
+            },
+        };
+
+        let mut encrypted_data = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+
+        encrypted_data.extend_from_slice(&nonce);
+        encrypted_data.extend_from_slice(&ciphertext);
+
+        Ok(encrypted_data)
+    }
+
+    /// Decrypt the data. It fails if the data or `associated_data` is not the same as when it was encrypted.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn decrypt(
+        &self,
+        data: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, io::Error> {
+        if data.len() < NONCE_SIZE {
+            return Err(io::Error::new(ErrorKind::InvalidData, "the encrypted data is too short"));
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+
+        let payload = Payload {
+            msg: ciphertext, aad: associated_data
+        };
+
+        let result = match self.algorithm {
+            EncryptionAlgorithm::Aes256Gcm => {
+                Aes256Gcm::new(&self.key.into()).decrypt(nonce.into(), payload)
+            },
+            EncryptionAlgorithm::ChaCha20Poly1305 => {
+                ChaCha20Poly1305::new(&self.key.into()).decrypt(nonce.into(), payload)
+            },
+        };
+
+        result.map_err(|_| io::Error::new(ErrorKind::InvalidData, "the data cannot be decrypted"))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    pub(crate) fn encrypt(
+        &self,
+        _data: &[u8],
+        _associated_data: &[u8],
+    ) -> Result<Vec<u8>, io::Error> {
+        Err(unsupported())
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    pub(crate) fn decrypt(
+        &self,
+        _data: &[u8],
+        _associated_data: &[u8],
+    ) -> Result<Vec<u8>, io::Error> {
+        Err(unsupported())
+    }
+
+    /// Decrypt a stream of the chunks of a file chunk by chunk. The stream has to start from the chunk whose index is `first_n`, and its chunks have to be contiguous.
+    pub(crate) fn decrypt_stream(
+        self,
+        stream: FileDataStream,
+        file_id: ObjectId,
+        first_n: i64,
+    ) -> FileDataStream {
+        let mut n = first_n;
+
+        Box::new(stream.map(move |chunk| {
+            let associated_data = chunk_associated_data(file_id, n);
+
+            n += 1;
+
+            chunk.and_then(|chunk| self.decrypt(chunk.get_ref(), &associated_data).map(Cursor::new))
+        }))
+    }
+}
+
+/// The keys for encrypting the data of files which are put into the file center. New files are encrypted with the current key, and files which were encrypted with older keys can still be decrypted as long as those keys are added.
+#[derive(Debug, Clone)]
+pub struct Encryption {
+    current_key_id: String,
+    keys:           HashMap<String, EncryptionKey>,
+}
+
+impl Encryption {
+    /// Create an `Encryption` instance whose current key is `current_key`.
+    #[inline]
+    pub fn new(current_key: EncryptionKey) -> Self {
+        let current_key_id = current_key.id.clone();
+
+        let mut keys = HashMap::new();
+
+        keys.insert(current_key_id.clone(), current_key);
+
+        Encryption {
+            current_key_id,
+            keys,
+        }
+    }
+
+    /// Add a key which is only used to decrypt files. If the ID is the same as the current key, the current key is replaced.
+    #[inline]
+    pub fn add_key(&mut self, key: EncryptionKey) {
+        self.keys.insert(key.id.clone(), key);
+    }
+
+    /// Get the key which new files are encrypted with.
+    #[inline]
+    pub fn get_current_key(&self) -> &EncryptionKey {
+        &self.keys[&self.current_key_id]
+    }
+
+    /// Get a key by its ID.
+    #[inline]
+    pub fn get_key(&self, id: &str) -> Option<&EncryptionKey> {
+        self.keys.get(id)
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     io::{self, Cursor, ErrorKind},
+    ops::{Bound, RangeBounds},
     path::Path,
     str::FromStr,
     time::Duration,
@@ -11,25 +12,34 @@ use crate::{
     bson::{
         document::{Document, ValueAccessError},
         oid::ObjectId,
-        spec::BinarySubtype,
-        Binary, Bson, DateTime,
+        Bson, DateTime, Regex,
     },
+    data_encoder::DataEncoder,
+    encryption::{chunk_associated_data, file_data_associated_data},
     functions::*,
+    integrity::{chunk_checksum, ChunkVerifier},
     mime::Mime,
     mongodb::{
+        error::{CommandError, ErrorKind as MongoErrorKind, WriteError, WriteFailure},
         options::{
-            ClientOptions, FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions,
-            ReturnDocument, UpdateOptions,
+            FindOneAndDeleteOptions, FindOneAndUpdateOptions, FindOneOptions, FindOptions,
+            IndexOptions, ReturnDocument, UpdateOptions,
         },
         results::DeleteResult,
         Client, Collection, Database, IndexModel,
     },
+    session::Session,
     tokio::{
+        self,
         fs::File,
-        io::{AsyncRead, AsyncReadExt},
+        io::{AsyncRead, AsyncReadExt, Take},
+        sync::mpsc,
     },
-    tokio_stream::{Stream, StreamExt},
-    Digest, FileCenterError, FileData, FileItem, Hasher, IDToken, DEFAULT_MIME_TYPE,
+    tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt},
+    ChunkBatchOptions, ChunkReader, ContentEncoding, Digest, DownloadOptions, Encryption,
+    EncryptionKey, Expiration, FileCenterBuilder, FileCenterError, FileData, FileDataStream,
+    FileInfo, FileItem, FileQuery, FileUploadStream, GarbageOptions, GarbageReport, Hasher,
+    IDToken, IncrementalGarbageOptions, ReadLimit, UploadOptions, DEFAULT_MIME_TYPE,
 };
 
 /// The default database name, if there is no database name in the MongoDB URI.
@@ -41,25 +51,53 @@ pub const COLLECTION_FILES_NAME: &str = "file_center";
 pub const COLLECTION_FILES_CHUNKS_NAME: &str = "file_center_chunks";
 /// The name of the collection which stores the settings of the file center.
 pub const COLLECTION_SETTINGS_NAME: &str = "file_center_settings";
+/// The name of the collection which stores the quotas and the usage counters of owners.
+pub const COLLECTION_QUOTAS_NAME: &str = "file_center_quotas";
 
 /// The name of the `file_size_threshold` value. When the file size is bigger than `file_size_threshold`, it should be separate into chunks to store in the `COLLECTION_FILES_CHUNKS_NAME` collection.
 ///
 /// `file_size_threshold` cannot bigger than `16_770_000`.
 pub const SETTING_FILE_SIZE_THRESHOLD: &str = "file_size_threshold";
+/// The name of the `chunk_size` value. The data of a file which is bigger than `file_size_threshold` is separated into chunks of this size.
+///
+/// `chunk_size` cannot bigger than `16_770_000`.
+pub const SETTING_CHUNK_SIZE: &str = "chunk_size";
 /// The name of the `create_time` value, which is the time instant that the file center is being created. The value is also used as the key of ID tokens.
 pub const SETTING_CREATE_TIME: &str = "create_time";
+/// The name of the `temporary_life_time` value, the default life time (in milliseconds) of temporary files.
+pub const SETTING_TEMPORARY_LIFE_TIME: &str = "temporary_life_time";
 /// The name of the `version` value, the version of this file center.
 pub const SETTING_VERSION: &str = "version";
+/// The name of the `garbage_checkpoint` value, the position which `clear_garbage_incrementally` resumes from.
+pub const SETTING_GARBAGE_CHECKPOINT: &str = "garbage_checkpoint";
+/// The name of the `maintenance_lease` value, which is held by the background maintenance task currently doing the work.
+pub const SETTING_MAINTENANCE_LEASE: &str = "maintenance_lease";
+
+const GARBAGE_PHASE_ITEMS: &str = "items";
+const GARBAGE_PHASE_CHUNKS: &str = "chunks";
 
 #[doc(hidden)]
 pub const MAX_FILE_SIZE_THRESHOLD: u32 = 16_770_000;
 #[doc(hidden)]
 pub const DEFAULT_FILE_SIZE_THRESHOLD: u32 = 262_144;
+#[doc(hidden)]
+pub const MAX_CHUNK_SIZE: u32 = 16_770_000;
+#[doc(hidden)]
+pub const DEFAULT_CHUNK_SIZE: u32 = 262_144;
+
+#[doc(hidden)]
+pub const DEFAULT_TEMPORARY_LIFE_TIME: Duration = Duration::from_secs(60);
+
+/// How long the chunks of a temporary file remain after the file item expires, so that a download stream which has been opened can still be finished.
+const TEMPORARY_CHUNK_EXTRA_LIFE_TIME: i64 = 3540000;
+
+const VERSION: i32 = 3; // Used for updating the database.
 
-const TEMPORARY_LIFE_TIME: i64 = 60000;
-const TEMPORARY_CHUNK_LIFE_TIME: i64 = 3600000;
+/// The name of the unique index of hashes which does not contain the encryption key ID.
+const LEGACY_HASH_INDEX_NAME: &str = "hash_1_1_hash_2_1_hash_3_1_hash_4_1";
 
-const VERSION: i32 = 2; // Used for updating the database.
+const ERROR_CODE_NAMESPACE_NOT_FOUND: i32 = 26;
+const ERROR_CODE_INDEX_NOT_FOUND: i32 = 27;
 
 #[inline]
 fn file_item_projection() -> Document {
@@ -71,7 +109,51 @@ fn file_item_projection() -> Document {
         "file_name": 1,
         "file_data": 1,
         "chunk_id": 1,
+        "chunk_size": 1,
+        "hash_1": 1,
+        "hash_2": 1,
+        "hash_3": 1,
+        "hash_4": 1,
+        "expire_at": 1,
+        "remaining_reads": 1,
+        "unlimited_reads": 1,
+        "metadata": 1,
+        "content_id": 1,
+        "content_encoding": 1,
+        "encryption_key_id": 1,
+    }
+}
+
+#[inline]
+fn file_info_projection() -> Document {
+    doc! {
+        "_id": 1,
+        "create_time": 1,
+        "mime_type": 1,
+        "file_size": 1,
+        "file_name": 1,
+        "count": 1,
+        "expire_at": 1,
+        "metadata": 1,
+        "content_id": 1,
+        "content_encoding": 1,
+    }
+}
+
+#[inline]
+fn file_range_projection() -> Document {
+    doc! {
+        "_id": 1,
+        "file_size": 1,
+        "file_data": 1,
+        "chunk_id": 1,
+        "chunk_size": 1,
         "expire_at": 1,
+        "remaining_reads": 1,
+        "unlimited_reads": 1,
+        "content_id": 1,
+        "content_encoding": 1,
+        "encryption_key_id": 1,
     }
 }
 
@@ -82,6 +164,14 @@ fn file_exist_projection() -> Document {
     }
 }
 
+#[inline]
+fn file_reference_projection() -> Document {
+    doc! {
+        "_id": 1,
+        "file_size": 1,
+    }
+}
+
 #[inline]
 fn file_item_delete_projection() -> Document {
     doc! {
@@ -89,34 +179,175 @@ fn file_item_delete_projection() -> Document {
         "count": 1,
         "chunk_id": 1,
         "file_size": 1,
+        "content_id": 1,
+        "detached": 1,
+        "expire_at": 1,
+        "owner": 1,
+    }
+}
+
+/// The fields of a reference which are not taken from its content.
+const REFERENCE_FIELDS: [&str; 5] = ["_id", "file_name", "mime_type", "create_time", "metadata"];
+
+/// Collect the Object IDs in the `key` field of the documents.
+pub(crate) async fn collect_ids(
+    mut cursor: impl Stream<Item = mongodb::error::Result<Document>> + Unpin,
+    key: &str,
+) -> Result<Vec<ObjectId>, FileCenterError> {
+    let mut ids = Vec::new();
+
+    while let Some(d) = cursor.try_next().await? {
+        ids.push(d.get_object_id(key)?);
+    }
+
+    Ok(ids)
+}
+
+#[inline]
+pub(crate) fn bson_to_u64(value: &Bson) -> Option<u64> {
+    match value {
+        Bson::Int32(v) => Some(*v as u64),
+        Bson::Int64(v) => Some(*v as u64),
+        Bson::Double(v) => Some(*v as u64),
+        _ => None,
+    }
+}
+
+#[inline]
+fn is_command_error(err: &mongodb::error::Error, codes: &[i32]) -> bool {
+    matches!(
+        err.kind.as_ref(),
+        MongoErrorKind::Command(CommandError {
+            code,
+            ..
+        }) if codes.contains(code)
+    )
+}
+
+#[inline]
+pub(crate) fn is_duplicate_key_error(err: &mongodb::error::Error) -> bool {
+    matches!(
+        err.kind.as_ref(),
+        MongoErrorKind::Write(WriteFailure::WriteError(WriteError {
+            code: 11000,
+            ..
+        }))
+    )
+}
+
+#[inline]
+fn insert_owner(file_item_raw: &mut Document, owner: Option<String>) {
+    if let Some(owner) = owner {
+        file_item_raw.insert("owner", owner);
+    }
+}
+
+#[inline]
+fn insert_read_limit(file_item_raw: &mut Document, read_limit: Option<ReadLimit>) {
+    match read_limit {
+        Some(ReadLimit::Limited(n)) => {
+            file_item_raw.insert("remaining_reads", n.max(1) as i64);
+        },
+        Some(ReadLimit::Unlimited) => {
+            file_item_raw.insert("unlimited_reads", true);
+        },
+        None => (),
+    }
+}
+
+pub(crate) fn get_content_encoding(
+    document: &Document,
+) -> Result<Option<ContentEncoding>, FileCenterError> {
+    match document.get("content_encoding") {
+        Some(Bson::String(content_encoding)) => Ok(Some(
+            ContentEncoding::from_str(content_encoding)
+                .map_err(|_| FileCenterError::DocumentError(ValueAccessError::UnexpectedType))?,
+        )),
+        Some(Bson::Null) | None => Ok(None),
+        Some(_) => Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType)),
     }
 }
 
 #[inline]
-fn chunk_document(file_id: ObjectId, n: i64, bytes: Vec<u8>) -> Document {
+pub(crate) fn chunk_document(file_id: ObjectId, n: i64, bytes: Vec<u8>) -> Document {
     doc! {
         "file_id": file_id,
         "n": n,
-        "data": bson::Binary{ subtype: bson::spec::BinarySubtype::Generic, bytes }
+        "checksum": chunk_checksum(&bytes),
+        "data": bson::Binary{ subtype: bson::spec::BinarySubtype::Generic, bytes },
+        "create_time": DateTime::now(),
+    }
+}
+
+#[inline]
+pub(crate) async fn insert_chunk(
+    collection_files_chunks: &Collection<Document>,
+    session: &mut Session,
+    file_id: ObjectId,
+    n: i64,
+    bytes: Vec<u8>,
+) -> Result<ObjectId, FileCenterError> {
+    let result =
+        session.insert_one(collection_files_chunks, chunk_document(file_id, n, bytes)).await?;
+
+    match result.inserted_id.as_object_id() {
+        Some(id) => Ok(id),
+        None => Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType)),
+    }
+}
+
+/// Insert a batch of chunks, and get the ID of the last one.
+pub(crate) async fn insert_chunks(
+    collection_files_chunks: &Collection<Document>,
+    session: &mut Session,
+    batch: Vec<Document>,
+) -> Result<ObjectId, FileCenterError> {
+    let last = batch.len() - 1;
+
+    let result = session.insert_many(collection_files_chunks, batch).await?;
+
+    match result.inserted_ids.get(&last).and_then(|id| id.as_object_id()) {
+        Some(id) => Ok(id),
+        None => Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType)),
     }
 }
 
 #[derive(Debug)]
-struct FileCenterCollections {
-    files:        Collection<Document>,
-    files_chunks: Collection<Document>,
-    settings:     Collection<Document>,
+pub(crate) struct FileCenterCollections {
+    pub(crate) files:        Collection<Document>,
+    pub(crate) files_chunks: Collection<Document>,
+    pub(crate) settings:     Collection<Document>,
+    pub(crate) quotas:       Collection<Document>,
+    /// The prefix of the names of the collections.
+    pub(crate) prefix:       String,
+}
+
+impl FileCenterCollections {
+    /// Get the name of a collection without the prefix.
+    #[inline]
+    fn base_name<'a>(&self, collection: &'a Collection<Document>) -> &'a str {
+        let name = collection.name();
+
+        name.strip_prefix(self.prefix.as_str()).unwrap_or(name)
+    }
 }
 
 /// To store perennial files and temporary files in MongoDB.
 #[derive(Debug)]
 pub struct FileCenter {
-    db:                  Database,
-    collections:         FileCenterCollections,
-    file_size_threshold: u32,
-    _create_time:        DateTime,
-    _version:            i32,
-    short_crypt:         ShortCrypt,
+    db:                     Database,
+    pub(crate) collections: FileCenterCollections,
+    file_size_threshold:    u32,
+    chunk_size:             u32,
+    temporary_life_time:    Duration,
+    transactions:           bool,
+    chunk_batch_options:    ChunkBatchOptions,
+    download_options:       DownloadOptions,
+    encryption:             Option<Encryption>,
+    create_indexes:         bool,
+    _create_time:           DateTime,
+    version:                i32,
+    short_crypt:            ShortCrypt,
 }
 
 impl FileCenter {
@@ -157,10 +388,15 @@ impl FileCenter {
                 index
             };
 
+            // the same content can be stored once for every encryption key, and once without encryption
             let hash_index = {
                 let mut options = IndexOptions::default();
                 options.unique = Some(true);
-                options.sparse = Some(true);
+                options.partial_filter_expression = Some(doc! {
+                    "hash_1": {
+                        "$exists": true
+                    }
+                });
 
                 let mut index = IndexModel::default();
 
@@ -168,7 +404,8 @@ impl FileCenter {
                     "hash_1": 1,
                     "hash_2": 1,
                     "hash_3": 1,
-                    "hash_4": 1
+                    "hash_4": 1,
+                    "encryption_key_id": 1
                 };
 
                 index.options = Some(options);
@@ -192,10 +429,32 @@ impl FileCenter {
                 index
             };
 
+            let owner_index = {
+                let mut options = IndexOptions::default();
+                options.sparse = Some(true);
+
+                let mut index = IndexModel::default();
+
+                index.keys = doc! {
+                    "owner": 1,
+                };
+
+                index.options = Some(options);
+
+                index
+            };
+
             self.collections
                 .files
                 .create_indexes(
-                    [create_time_index, expire_at_index, count_index, hash_index, chunk_id_index],
+                    [
+                        create_time_index,
+                        expire_at_index,
+                        count_index,
+                        hash_index,
+                        chunk_id_index,
+                        owner_index,
+                    ],
                     None,
                 )
                 .await?;
@@ -236,47 +495,60 @@ impl FileCenter {
         Ok(())
     }
 
-    async fn new_with_file_size_threshold_inner<U: AsRef<str>>(
-        uri: U,
-        initial_file_size_threshold: u32,
-    ) -> Result<FileCenter, FileCenterError> {
-        let uri = uri.as_ref();
-
-        let client_options = ClientOptions::parse(uri).await?;
-
-        let client = Client::with_options(client_options)?;
-
-        // TODO in the future, the client_options should have the default_database method
-
-        let db_name = {
-            let uri = &uri[(uri.find("://").unwrap() + 3)..];
-
-            if let Some(index) = uri.rfind('/') {
-                let start = index + 1;
-
-                let end = uri[start..]
-                    .rfind('?')
-                    .unwrap_or_else(|| uri[start..].rfind('#').unwrap_or(uri.len()));
-
-                if start == end {
-                    DEFAULT_DATABASE_NAME
-                } else {
-                    &uri[start..end]
+    /// Update a database which was created by an older version to `VERSION`.
+    async fn upgrade(&mut self) -> Result<(), FileCenterError> {
+        // version 3 deduplicates the content for each encryption key, so the unique hash index without the encryption key ID has to be replaced. The fields which are added by version 3 are optional, so the documents are kept as they are
+        if self.version < 3 {
+            if let Err(err) = self.collections.files.drop_index(LEGACY_HASH_INDEX_NAME, None).await
+            {
+                if !is_command_error(&err, &[
+                    ERROR_CODE_NAMESPACE_NOT_FOUND,
+                    ERROR_CODE_INDEX_NOT_FOUND,
+                ]) {
+                    return Err(err.into());
                 }
-            } else {
-                DEFAULT_DATABASE_NAME
             }
-        };
+        }
+
+        self.collections
+            .settings
+            .update_one(
+                doc! {
+                    "_id": SETTING_VERSION,
+                    "value": {
+                        "$lt": VERSION
+                    }
+                },
+                doc! {
+                    "$set": {
+                        "value": VERSION
+                    }
+                },
+                None,
+            )
+            .await?;
 
-        let db = client.database(db_name);
+        self.version = VERSION;
 
+        Ok(())
+    }
+
+    /// Load the settings of a file center, or store the initial ones if they do not exist, and create the indexes if `create_indexes` is `true`.
+    pub(crate) async fn open(
+        db: Database,
+        collections: FileCenterCollections,
+        initial_file_size_threshold: u32,
+        initial_chunk_size: u32,
+        initial_temporary_life_time: Duration,
+        create_indexes: bool,
+    ) -> Result<FileCenter, FileCenterError> {
         let file_size_threshold;
+        let chunk_size;
+        let temporary_life_time;
         let create_time;
         let version;
 
-        let collection_settings = db.collection::<Document>(COLLECTION_SETTINGS_NAME);
-        let collection_files = db.collection::<Document>(COLLECTION_FILES_NAME);
-        let collection_files_chunks = db.collection::<Document>(COLLECTION_FILES_CHUNKS_NAME);
+        let collection_settings = &collections.settings;
 
         {
             file_size_threshold = match collection_settings
@@ -318,6 +590,78 @@ impl FileCenter {
                 },
             };
 
+            chunk_size = match collection_settings
+                .find_one(
+                    Some(doc! {
+                        "_id": SETTING_CHUNK_SIZE
+                    }),
+                    None,
+                )
+                .await?
+            {
+                Some(chunk_size) => {
+                    let chunk_size = chunk_size.get_i32("value")?;
+
+                    if chunk_size <= 0 {
+                        return Err(FileCenterError::ChunkSizeError);
+                    }
+
+                    let chunk_size = chunk_size as u32;
+
+                    if chunk_size > MAX_CHUNK_SIZE {
+                        return Err(FileCenterError::ChunkSizeError);
+                    }
+
+                    chunk_size
+                },
+                None => {
+                    collection_settings
+                        .insert_one(
+                            doc! {
+                                "_id": SETTING_CHUNK_SIZE,
+                                "value": initial_chunk_size
+                            },
+                            None,
+                        )
+                        .await?;
+
+                    initial_chunk_size
+                },
+            };
+
+            temporary_life_time = match collection_settings
+                .find_one(
+                    Some(doc! {
+                        "_id": SETTING_TEMPORARY_LIFE_TIME
+                    }),
+                    None,
+                )
+                .await?
+            {
+                Some(temporary_life_time) => {
+                    let temporary_life_time = temporary_life_time.get_i64("value")?;
+
+                    if temporary_life_time <= 0 {
+                        return Err(FileCenterError::TemporaryLifeTimeError);
+                    }
+
+                    Duration::from_millis(temporary_life_time as u64)
+                },
+                None => {
+                    collection_settings
+                        .insert_one(
+                            doc! {
+                                "_id": SETTING_TEMPORARY_LIFE_TIME,
+                                "value": initial_temporary_life_time.as_millis() as i64
+                            },
+                            None,
+                        )
+                        .await?;
+
+                    initial_temporary_life_time
+                },
+            };
+
             create_time = match collection_settings
                 .find_one(
                     Some(doc! {
@@ -386,44 +730,107 @@ impl FileCenter {
             };
         }
 
-        let short_crypt = ShortCrypt::new(format!("FileCenter-{}", create_time.timestamp_millis()));
+        // the key of a file center with custom collections also depends on their names, so that buckets created at the same time do not share the same key
+        let short_crypt = match collections.settings.name() {
+            COLLECTION_SETTINGS_NAME => {
+                ShortCrypt::new(format!("FileCenter-{}", create_time.timestamp_millis()))
+            },
+            name => {
+                ShortCrypt::new(format!("FileCenter-{}-{}", name, create_time.timestamp_millis()))
+            },
+        };
 
-        let file_center = FileCenter {
+        let mut file_center = FileCenter {
             db,
-            collections: FileCenterCollections {
-                files:        collection_files,
-                files_chunks: collection_files_chunks,
-                settings:     collection_settings,
-            },
+            collections,
             file_size_threshold,
+            chunk_size,
+            temporary_life_time,
+            transactions: false,
+            chunk_batch_options: ChunkBatchOptions::default(),
+            download_options: DownloadOptions::default(),
+            encryption: None,
+            create_indexes,
             _create_time: create_time,
-            _version: version,
+            version,
             short_crypt,
         };
 
-        file_center.create_indexes().await?;
+        if file_center.version < VERSION {
+            file_center.upgrade().await?;
+        }
+
+        if create_indexes {
+            file_center.create_indexes().await?;
+        }
 
         Ok(file_center)
     }
 
-    /// Create a new FileCenter instance.
+    /// Create a new FileCenter instance. It is the same as `FileCenterBuilder::from_uri(uri).build()`.
     #[inline]
     pub async fn new<U: AsRef<str>>(uri: U) -> Result<FileCenter, FileCenterError> {
-        Self::new_with_file_size_threshold_inner(uri, DEFAULT_FILE_SIZE_THRESHOLD).await
+        FileCenterBuilder::from_uri(uri).build().await
     }
 
-    /// Create a new FileCenter instance with a custom initial file size threshold.
+    /// Create a new FileCenter instance with a custom initial file size threshold. It is the same as `FileCenterBuilder::from_uri(uri).file_size_threshold(initial_file_size_threshold).build()`.
     #[inline]
     pub async fn new_with_file_size_threshold<U: AsRef<str>>(
         uri: U,
         initial_file_size_threshold: u32,
     ) -> Result<FileCenter, FileCenterError> {
-        if initial_file_size_threshold > MAX_FILE_SIZE_THRESHOLD || initial_file_size_threshold == 0
-        {
-            return Err(FileCenterError::FileSizeThresholdError);
+        FileCenterBuilder::from_uri(uri)
+            .file_size_threshold(initial_file_size_threshold)
+            .build()
+            .await
+    }
+
+    /// Create a new FileCenter instance on an existing database, so that the connection pool and the other settings of its client are shared. It is the same as `FileCenterBuilder::from_database(db).build()`.
+    #[inline]
+    pub async fn new_with_database(db: Database) -> Result<FileCenter, FileCenterError> {
+        FileCenterBuilder::from_database(db).build().await
+    }
+
+    /// Create a new FileCenter instance on a database of an existing client, so that the connection pool and the other settings of the client are shared. It is the same as `FileCenterBuilder::from_client(client).database_name(database_name).build()`.
+    #[inline]
+    pub async fn new_with_client<S: Into<String>>(
+        client: Client,
+        database_name: S,
+    ) -> Result<FileCenter, FileCenterError> {
+        FileCenterBuilder::from_client(client).database_name(database_name).build().await
+    }
+
+    /// Open a bucket, which is another file center in the same database, using the same client. Its collections have the same names as the collections of this instance, with `"<name>_"` inserted after the collection prefix, and it has its own settings, such as the file size threshold and the key of ID tokens. The options which are not stored in the database, such as the encryption keys, are copied from this instance.
+    ///
+    /// The name must not be empty or contain `$` or the null character. Use `drop_file_center` to remove a bucket, because `drop_database` removes every bucket in the database.
+    pub async fn bucket<S: Into<String>>(&self, name: S) -> Result<FileCenter, FileCenterError> {
+        let collections = &self.collections;
+
+        let mut builder = FileCenterBuilder::from_database(self.db.clone())
+            .files_collection_name(collections.base_name(&collections.files))
+            .files_chunks_collection_name(collections.base_name(&collections.files_chunks))
+            .settings_collection_name(collections.base_name(&collections.settings))
+            .quotas_collection_name(collections.base_name(&collections.quotas))
+            .collection_prefix(collections.prefix.as_str())
+            .bucket(name)
+            .create_indexes(self.create_indexes);
+
+        if let Some(read_concern) = self.collections.files.read_concern() {
+            builder = builder.read_concern(read_concern.clone());
+        }
+
+        if let Some(write_concern) = self.collections.files.write_concern() {
+            builder = builder.write_concern(write_concern.clone());
         }
 
-        Self::new_with_file_size_threshold_inner(uri, initial_file_size_threshold).await
+        let mut file_center = builder.build().await?;
+
+        file_center.transactions = self.transactions;
+        file_center.chunk_batch_options = self.chunk_batch_options;
+        file_center.download_options = self.download_options;
+        file_center.encryption = self.encryption.clone();
+
+        Ok(file_center)
     }
 }
 
@@ -469,58 +876,452 @@ impl FileCenter {
         Ok(())
     }
 
-    /// Drop the database.
+    /// Get the size of chunks which the data of a big file is separated into.
     #[inline]
-    pub async fn drop_database(self) -> Result<(), FileCenterError> {
-        self.db.drop(None).await?;
+    pub const fn get_chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// Change the size of chunks which the data of a big file is separated into. The files which have been stored are not affected.
+    pub async fn set_chunk_size(&mut self, chunk_size: u32) -> Result<(), FileCenterError> {
+        let collection_settings = &self.collections.settings;
+
+        if chunk_size > MAX_CHUNK_SIZE || chunk_size == 0 {
+            return Err(FileCenterError::ChunkSizeError);
+        }
+
+        if chunk_size != self.chunk_size {
+            let mut options = UpdateOptions::default();
+            options.upsert = Some(true);
+
+            collection_settings
+                .update_one(
+                    doc! {
+                        "_id": SETTING_CHUNK_SIZE
+                    },
+                    doc! {
+                        "$set": {
+                            "value": chunk_size
+                        }
+                    },
+                    Some(options),
+                )
+                .await?;
+
+            self.chunk_size = chunk_size;
+        }
 
         Ok(())
     }
 
-    /// Drop the file center. But remain the database.
+    /// Get the default life time of temporary files.
     #[inline]
-    pub async fn drop_file_center(self) -> Result<(), FileCenterError> {
-        self.collections.files.drop(None).await?;
-        self.collections.files_chunks.drop(None).await?;
-        self.collections.settings.drop(None).await?;
-
-        Ok(())
+    pub const fn get_temporary_life_time(&self) -> Duration {
+        self.temporary_life_time
     }
-}
 
-impl FileCenter {
-    async fn open_download_stream(
-        &self,
-        id: ObjectId,
-    ) -> Result<impl Stream<Item = Result<Cursor<Vec<u8>>, io::Error>> + Unpin, FileCenterError>
-    {
-        let collection_files_chunks = &self.collections.files_chunks;
+    /// Change the default life time of temporary files. It must be at least one millisecond and cannot be longer than `i64::MAX` milliseconds.
+    pub async fn set_temporary_life_time(
+        &mut self,
+        temporary_life_time: Duration,
+    ) -> Result<(), FileCenterError> {
+        let collection_settings = &self.collections.settings;
 
-        let mut find_options = FindOptions::default();
+        let millis = temporary_life_time.as_millis();
 
-        find_options.sort = Some(doc! {
-            "n": 1
-        });
+        if millis == 0 || millis > i64::MAX as u128 {
+            return Err(FileCenterError::TemporaryLifeTimeError);
+        }
 
-        Ok(collection_files_chunks
-            .find(
-                doc! {
-                    "file_id": id
-                },
-                find_options,
-            )
-            .await
-            .unwrap()
-            .map(|item| {
-                item.map_err(|err| io::Error::new(ErrorKind::InvalidData, err)).and_then(|i| {
-                    i.get_binary_generic("data")
-                        .map(|v| Cursor::new(v.to_vec()))
-                        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
-                })
-            }))
-    }
+        if temporary_life_time != self.temporary_life_time {
+            let mut options = UpdateOptions::default();
+            options.upsert = Some(true);
 
-    async fn create_file_item(&self, mut document: Document) -> Result<FileItem, FileCenterError> {
+            collection_settings
+                .update_one(
+                    doc! {
+                        "_id": SETTING_TEMPORARY_LIFE_TIME
+                    },
+                    doc! {
+                        "$set": {
+                            "value": millis as i64
+                        }
+                    },
+                    Some(options),
+                )
+                .await?;
+
+            self.temporary_life_time = temporary_life_time;
+        }
+
+        Ok(())
+    }
+
+    /// Check whether uploads and deletions are run in transactions.
+    #[inline]
+    pub const fn is_transactions_enabled(&self) -> bool {
+        self.transactions
+    }
+
+    /// Enable or disable running uploads and deletions in transactions. When it is enabled, the chunks and the file item of an upload are stored all-or-nothing, and so are the steps of a deletion. Transactions are only supported by replica sets and sharded clusters. They are also limited in running time, which is 60 seconds by default, so very large uploads may need the limit to be raised on the server.
+    ///
+    /// A transaction which fails with a transient error, such as a write conflict with a concurrent upload of the same content, is run again, in the same way as the `with_transaction` helpers of the MongoDB drivers. Uploads from a reader or an upload stream cannot read their data again, so they fail with the error instead, and have to be retried by the caller.
+    pub async fn set_transactions_enabled(&mut self, enabled: bool) -> Result<(), FileCenterError> {
+        if enabled && !self.transactions {
+            let hello = self
+                .db
+                .run_command(
+                    doc! {
+                        "hello": 1
+                    },
+                    None,
+                )
+                .await?;
+
+            let is_replica_set = hello.get_str("setName").is_ok();
+            let is_sharded_cluster = matches!(hello.get_str("msg"), Ok("isdbgrid"));
+
+            if !is_replica_set && !is_sharded_cluster {
+                return Err(FileCenterError::TransactionsNotSupportedError);
+            }
+        }
+
+        self.transactions = enabled;
+
+        Ok(())
+    }
+
+    /// Get the options of inserting chunks in batches.
+    #[inline]
+    pub const fn get_chunk_batch_options(&self) -> ChunkBatchOptions {
+        self.chunk_batch_options
+    }
+
+    /// Change the options of inserting chunks in batches. They are not stored in the database.
+    #[inline]
+    pub fn set_chunk_batch_options(&mut self, chunk_batch_options: ChunkBatchOptions) {
+        self.chunk_batch_options = chunk_batch_options;
+    }
+
+    /// Get the options of downloading files as streams.
+    #[inline]
+    pub const fn get_download_options(&self) -> DownloadOptions {
+        self.download_options
+    }
+
+    /// Change the options of downloading files as streams. They are not stored in the database.
+    #[inline]
+    pub fn set_download_options(&mut self, download_options: DownloadOptions) {
+        self.download_options = download_options;
+    }
+
+    /// Get the keys for encrypting the data of files.
+    #[inline]
+    pub fn get_encryption(&self) -> Option<&Encryption> {
+        self.encryption.as_ref()
+    }
+
+    /// Change the keys for encrypting the data of files. Files put after this are encrypted with the current key. The keys are not stored in the database, so the same keys have to be set every time the file center is created, or the encrypted files cannot be read.
+    ///
+    /// A file is only deduplicated into a stored file which is encrypted with the same key. The SHA-256 hash of a perennial file is stored in plain next to its encrypted data, so anyone who can read the database can still confirm whether a file whose content is known is stored.
+    #[inline]
+    pub fn set_encryption(&mut self, encryption: Option<Encryption>) {
+        self.encryption = encryption;
+    }
+
+    /// Get the encoder for the data of a file which is being put.
+    #[inline]
+    pub(crate) fn get_data_encoder(
+        &self,
+        upload_options: &UploadOptions,
+        mime_type: &Mime,
+    ) -> DataEncoder {
+        DataEncoder {
+            content_encoding: upload_options.get_content_encoding(mime_type),
+            encryption_key:   self
+                .encryption
+                .as_ref()
+                .map(|encryption| encryption.get_current_key().clone()),
+        }
+    }
+
+    /// Get the maximum size of the data of a file which is stored in its file item, so that the file item along with the file name and the metadata does not exceed the size limit of a BSON document. Bigger data is separated into chunks.
+    pub(crate) fn get_inline_threshold(
+        &self,
+        file_name: &str,
+        upload_options: &UploadOptions,
+    ) -> Result<u64, FileCenterError> {
+        let metadata_size = match upload_options.metadata.as_ref() {
+            Some(metadata) => bson::to_vec(metadata)
+                .map_err(|_| FileCenterError::DocumentError(ValueAccessError::UnexpectedType))?
+                .len(),
+            None => 0,
+        };
+
+        // the other fields of a file item fit in the space between `MAX_FILE_SIZE_THRESHOLD` and the size limit
+        let size = metadata_size.saturating_add(file_name.len()) as u64;
+
+        if size > MAX_FILE_SIZE_THRESHOLD as u64 {
+            return Err(FileCenterError::MetadataTooLargeError);
+        }
+
+        Ok((self.file_size_threshold as u64).min(MAX_FILE_SIZE_THRESHOLD as u64 - size))
+    }
+
+    /// Get the key which the data of a file item has been encrypted with.
+    pub(crate) fn get_decryption_key(
+        &self,
+        document: &Document,
+    ) -> Result<Option<EncryptionKey>, FileCenterError> {
+        match document.get("encryption_key_id") {
+            Some(Bson::String(key_id)) => {
+                match self.encryption.as_ref().and_then(|encryption| encryption.get_key(key_id)) {
+                    Some(key) => Ok(Some(key.clone())),
+                    None => Err(FileCenterError::EncryptionKeyNotFoundError(key_id.clone())),
+                }
+            },
+            Some(Bson::Null) | None => Ok(None),
+            Some(_) => Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType)),
+        }
+    }
+
+    /// Start a session which runs a transaction if transactions are enabled.
+    #[inline]
+    pub(crate) async fn start_session(&self) -> Result<Session, FileCenterError> {
+        Ok(Session::start(self.collections.files.client(), self.transactions).await?)
+    }
+
+    /// Check that a temporary file would not have expired when it is put.
+    fn check_temporary_expiration(expiration: Option<Expiration>) -> Result<(), FileCenterError> {
+        match expiration {
+            Some(Expiration::At(expire_at)) if expire_at <= DateTime::now() => {
+                Err(FileCenterError::TemporaryLifeTimeError)
+            },
+            Some(Expiration::After(life_time)) if life_time.as_millis() == 0 => {
+                Err(FileCenterError::TemporaryLifeTimeError)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Compute the expiration time of a temporary file which is created at `now`.
+    fn get_temporary_expiration(&self, now: DateTime, expiration: Option<Expiration>) -> DateTime {
+        match expiration {
+            Some(Expiration::At(expire_at)) => expire_at,
+            Some(Expiration::After(life_time)) => DateTime::from_millis(
+                now.timestamp_millis()
+                    .saturating_add(life_time.as_millis().min(i64::MAX as u128) as i64),
+            ),
+            None => DateTime::from_millis(
+                now.timestamp_millis().saturating_add(self.temporary_life_time.as_millis() as i64),
+            ),
+        }
+    }
+
+    /// Drop the database, including all the file centers and buckets stored in it. Use `drop_file_center` to drop only this one.
+    #[inline]
+    pub async fn drop_database(self) -> Result<(), FileCenterError> {
+        self.db.drop(None).await?;
+
+        Ok(())
+    }
+
+    /// Drop the file center. But remain the database.
+    #[inline]
+    pub async fn drop_file_center(self) -> Result<(), FileCenterError> {
+        self.collections.files.drop(None).await?;
+        self.collections.files_chunks.drop(None).await?;
+        self.collections.settings.drop(None).await?;
+        self.collections.quotas.drop(None).await?;
+
+        Ok(())
+    }
+}
+
+impl FileCenter {
+    /// Read the chunks ahead in the background if it is enabled.
+    fn prefetch_chunks(
+        &self,
+        stream: impl Stream<Item = Result<Cursor<Vec<u8>>, io::Error>> + Unpin + Send + 'static,
+    ) -> FileDataStream {
+        let read_ahead = self.download_options.read_ahead;
+
+        if read_ahead == 0 {
+            return Box::new(stream);
+        }
+
+        let (sender, receiver) = mpsc::channel(read_ahead);
+
+        tokio::spawn(async move {
+            let mut stream = stream;
+
+            while let Some(chunk) = stream.next().await {
+                let is_err = chunk.is_err();
+
+                // the receiver has been dropped, or an error has been sent
+                if sender.send(chunk).await.is_err() || is_err {
+                    break;
+                }
+            }
+        });
+
+        Box::new(ReceiverStream::new(receiver))
+    }
+
+    /// Open a stream of the chunks of a file. If `last_n` is given, the stream fails when any chunk before it is missing.
+    async fn open_download_stream(
+        &self,
+        id: ObjectId,
+        last_n: Option<i64>,
+    ) -> Result<FileDataStream, FileCenterError> {
+        let collection_files_chunks = &self.collections.files_chunks;
+
+        let mut find_options = FindOptions::default();
+
+        find_options.sort = Some(doc! {
+            "n": 1
+        });
+        find_options.batch_size = self.download_options.batch_size;
+
+        let cursor = collection_files_chunks
+            .find(
+                doc! {
+                    "file_id": id
+                },
+                find_options,
+            )
+            .await?;
+
+        let stream = ChunkVerifier::new(cursor, 0, last_n).map(|item| {
+            item.and_then(|i| {
+                i.get_binary_generic("data")
+                    .map(|v| Cursor::new(v.to_vec()))
+                    .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+            })
+        });
+
+        Ok(self.prefetch_chunks(stream))
+    }
+
+    /// Get the size of the first chunk, which is also the size of every chunk except the last one.
+    async fn find_first_chunk_size(&self, id: ObjectId) -> Result<Option<u64>, FileCenterError> {
+        let mut options = FindOneOptions::default();
+        options.projection = Some(doc! {
+            "_id": 0,
+            "data": 1,
+        });
+
+        let chunk = self
+            .collections
+            .files_chunks
+            .find_one(
+                Some(doc! {
+                    "file_id": id,
+                    "n": 0i64
+                }),
+                Some(options),
+            )
+            .await?;
+
+        match chunk {
+            Some(chunk) => {
+                let chunk_size = chunk.get_binary_generic("data")?.len() as u64;
+
+                if chunk_size == 0 {
+                    Ok(None)
+                } else {
+                    Ok(Some(chunk_size))
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    async fn open_download_range_stream(
+        &self,
+        id: ObjectId,
+        chunk_size: u64,
+        decryption_key: Option<EncryptionKey>,
+        content_encoding: Option<ContentEncoding>,
+        start: u64,
+        end: u64,
+    ) -> Result<FileDataStream, FileCenterError> {
+        let collection_files_chunks = &self.collections.files_chunks;
+
+        let first_n = (start / chunk_size) as i64;
+        let last_n = ((end - 1) / chunk_size) as i64;
+
+        let mut find_options = FindOptions::default();
+
+        find_options.sort = Some(doc! {
+            "n": 1
+        });
+        find_options.batch_size = self.download_options.batch_size;
+
+        let cursor = collection_files_chunks
+            .find(
+                doc! {
+                    "file_id": id,
+                    "n": {
+                        "$gte": first_n,
+                        "$lte": last_n
+                    }
+                },
+                find_options,
+            )
+            .await?;
+
+        let stream = ChunkVerifier::new(cursor, first_n, Some(last_n)).map(move |item| {
+            item.and_then(|i| {
+                let n =
+                    i.get_i64("n").map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+                let data = i
+                    .get_binary_generic("data")
+                    .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+                // the offsets are counted on the original data, so the chunk has to be decrypted and decoded first
+                let decrypted_data;
+
+                let data = match decryption_key.as_ref() {
+                    Some(decryption_key) => {
+                        decrypted_data =
+                            decryption_key.decrypt(data, &chunk_associated_data(id, n))?;
+
+                        &decrypted_data
+                    },
+                    None => data,
+                };
+
+                let decoded_data;
+
+                let data = match content_encoding {
+                    Some(content_encoding) => {
+                        decoded_data = content_encoding.decode(data)?;
+
+                        &decoded_data
+                    },
+                    None => data,
+                };
+
+                let offset = n as u64 * chunk_size;
+
+                let data_start = if n == first_n { (start - offset) as usize } else { 0 };
+
+                let data_end = if n == last_n {
+                    ((end - offset) as usize).min(data.len())
+                } else {
+                    data.len()
+                };
+
+                Ok(Cursor::new(data[data_start.min(data_end)..data_end].to_vec()))
+            })
+        });
+
+        Ok(self.prefetch_chunks(stream))
+    }
+
+    fn create_file_info(mut document: Document) -> Result<FileInfo, FileCenterError> {
         let file_id = match document
             .remove("_id")
             .ok_or(FileCenterError::DocumentError(ValueAccessError::NotPresent))?
@@ -574,16 +1375,76 @@ impl FileCenter {
             },
         };
 
-        let file_data = match document.remove("file_data") {
+        let metadata = match document.remove("metadata") {
+            Some(metadata) => match metadata {
+                Bson::Document(b) => Some(b),
+                _ => {
+                    return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
+                },
+            },
+            None => None,
+        };
+
+        let count = match document.get("count") {
+            Some(count) => match count.as_i32() {
+                Some(count) => count,
+                None => {
+                    return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
+                },
+            },
+            None => 1,
+        };
+
+        let content_encoding = get_content_encoding(&document)?;
+
+        Ok(FileInfo {
+            file_id,
+            create_time,
+            expire_at,
+            mime_type,
+            file_size,
+            file_name,
+            metadata,
+            count,
+            content_encoding,
+        })
+    }
+
+    async fn create_file_item(&self, mut document: Document) -> Result<FileItem, FileCenterError> {
+        let file_data = document.remove("file_data");
+        let chunk_id = document.remove("chunk_id");
+        let content_id = document.get_object_id("content_id").ok();
+        let decryption_key = self.get_decryption_key(&document)?;
+        let chunk_size = document.get("chunk_size").and_then(|chunk_size| chunk_size.as_i32());
+        let hash = match (
+            document.get_i64("hash_1"),
+            document.get_i64("hash_2"),
+            document.get_i64("hash_3"),
+            document.get_i64("hash_4"),
+        ) {
+            (Ok(hash_1), Ok(hash_2), Ok(hash_3), Ok(hash_4)) => {
+                Some((hash_1, hash_2, hash_3, hash_4))
+            },
+            _ => None,
+        };
+
+        let file_info = Self::create_file_info(document)?;
+
+        let file_data = match file_data {
             Some(file_data) => match file_data {
-                Bson::Binary(b) => FileData::Buffer(b.bytes),
+                Bson::Binary(b) => match decryption_key {
+                    Some(decryption_key) => FileData::Buffer(decryption_key.decrypt(
+                        &b.bytes,
+                        &file_data_associated_data(content_id.unwrap_or(file_info.file_id)),
+                    )?),
+                    None => FileData::Buffer(b.bytes),
+                },
                 _ => {
                     return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
                 },
             },
             None => {
-                match document
-                    .remove("chunk_id")
+                match chunk_id
                     .ok_or(FileCenterError::DocumentError(ValueAccessError::NotPresent))?
                 {
                     Bson::ObjectId(_) => (),
@@ -594,19 +1455,36 @@ impl FileCenter {
                     },
                 };
 
-                let stream = self.open_download_stream(file_id).await?;
+                let last_n = match chunk_size {
+                    Some(chunk_size) if chunk_size > 0 => {
+                        Some((file_info.file_size.max(1) - 1) as i64 / chunk_size as i64)
+                    },
+                    _ => None,
+                };
+
+                let content_id = content_id.unwrap_or(file_info.file_id);
+
+                let stream = self.open_download_stream(content_id, last_n).await?;
 
-                FileData::Stream(Box::new(stream))
+                match decryption_key {
+                    Some(decryption_key) => {
+                        FileData::Stream(decryption_key.decrypt_stream(stream, content_id, 0))
+                    },
+                    None => FileData::Stream(stream),
+                }
             },
         };
 
         Ok(FileItem {
-            file_id,
-            create_time,
-            expire_at,
-            mime_type,
-            file_size,
-            file_name,
+            file_id: file_info.file_id,
+            create_time: file_info.create_time,
+            expire_at: file_info.expire_at,
+            mime_type: file_info.mime_type,
+            file_size: file_info.file_size,
+            file_name: file_info.file_name,
+            metadata: file_info.metadata,
+            content_encoding: file_info.content_encoding,
+            hash,
             file_data,
         })
     }
@@ -621,7 +1499,10 @@ impl FileCenter {
             .files
             .find_one(
                 Some(doc! {
-                    "_id": id
+                    "_id": id,
+                    "detached": {
+                        "$ne": true
+                    }
                 }),
                 Some(options),
             )
@@ -630,20 +1511,68 @@ impl FileCenter {
         Ok(file_item.is_some())
     }
 
-    /// Get the file item via an Object ID.
-    pub async fn get_file_item_by_id(
+    /// If the document is a reference, fill it with the fields of the file content it refers to. Returns `None` if the file content does not exist.
+    async fn resolve_file_reference(
         &self,
-        id: ObjectId,
-    ) -> Result<Option<FileItem>, FileCenterError> {
-        let collection_files = &self.collections.files;
+        mut document: Document,
+        projection: Document,
+    ) -> Result<Option<Document>, FileCenterError> {
+        let content_id = match document.get("content_id") {
+            Some(content_id) => match content_id.as_object_id() {
+                Some(content_id) => content_id,
+                None => {
+                    return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
+                },
+            },
+            None => return Ok(Some(document)),
+        };
 
         let mut options = FindOneOptions::default();
-        options.projection = Some(file_item_projection());
+        options.projection = Some(projection);
 
-        let file_item = collection_files
+        let content = self
+            .collections
+            .files
             .find_one(
                 Some(doc! {
-                    "_id": id
+                    "_id": content_id
+                }),
+                Some(options),
+            )
+            .await?;
+
+        match content {
+            Some(content) => {
+                for (key, value) in content {
+                    if !REFERENCE_FIELDS.contains(&key.as_str()) {
+                        document.insert(key, value);
+                    }
+                }
+
+                Ok(Some(document))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Find a file document. If the file is temporary, it will be removed from the database.
+    async fn find_file_document(
+        &self,
+        id: ObjectId,
+        projection: Document,
+    ) -> Result<Option<Document>, FileCenterError> {
+        let collection_files = &self.collections.files;
+
+        let mut options = FindOneOptions::default();
+        options.projection = Some(projection.clone());
+
+        let file_item = collection_files
+            .find_one(
+                Some(doc! {
+                    "_id": id,
+                    "detached": {
+                        "$ne": true
+                    }
                 }),
                 Some(options),
             )
@@ -654,7 +1583,13 @@ impl FileCenter {
                 if let Some(expire_at) = file_item.get("expire_at") {
                     match expire_at.as_datetime() {
                         Some(expire_at) => {
-                            if collection_files
+                            if file_item.get_bool("unlimited_reads").unwrap_or(false) {
+                                // the file can be read until it expires
+                            } else if file_item.contains_key("remaining_reads") {
+                                if !self.decrease_remaining_reads(id).await? {
+                                    return Ok(None);
+                                }
+                            } else if collection_files
                                 .delete_one(
                                     doc! {
                                         "_id": id
@@ -679,33 +1614,34 @@ impl FileCenter {
                     }
                 }
 
-                let file_item = self.create_file_item(file_item).await?;
-
-                Ok(Some(file_item))
+                self.resolve_file_reference(file_item, projection).await
             },
             None => Ok(None),
         }
     }
 
-    /// Remove a file item via an Object ID.
-    pub async fn delete_file_item_by_id(
-        &self,
-        file_id: ObjectId,
-    ) -> Result<Option<u64>, FileCenterError> {
+    /// Atomically consume one read of a temporary file, and remove the file if no reads remain. Returns `false` if there is no read left.
+    async fn decrease_remaining_reads(&self, id: ObjectId) -> Result<bool, FileCenterError> {
         let collection_files = &self.collections.files;
 
         let mut options = FindOneAndUpdateOptions::default();
         options.return_document = Some(ReturnDocument::After);
-        options.projection = Some(file_item_delete_projection());
+        options.projection = Some(doc! {
+            "_id": 0,
+            "remaining_reads": 1,
+        });
 
         let result = collection_files
             .find_one_and_update(
                 doc! {
-                   "_id": file_id,
+                    "_id": id,
+                    "remaining_reads": {
+                        "$gt": 0
+                    }
                 },
                 doc! {
                     "$inc": {
-                        "count": -1
+                        "remaining_reads": -1
                     }
                 },
                 Some(options),
@@ -714,402 +1650,747 @@ impl FileCenter {
 
         match result {
             Some(result) => {
-                let count = result.get_i32("count")?;
-                let file_size = result.get_i64("file_size")? as u64;
-
-                if count <= 0 {
-                    collection_files
+                if result.get_i64("remaining_reads")? <= 0
+                    && collection_files
                         .delete_one(
                             doc! {
-                                "_id": file_id
+                                "_id": id
                             },
                             None,
                         )
-                        .await?;
-
-                    if result.get("chunk_id").is_some()
-                        && self.delete_file_chunks(file_id).await.is_err()
-                    {}
+                        .await
+                        .is_err()
+                {
+                    // do nothing
                 }
 
-                Ok(Some(file_size))
+                Ok(true)
             },
-            None => Ok(None),
+            None => Ok(false),
         }
     }
-}
 
-impl FileCenter {
-    #[inline]
-    async fn delete_file_chunks(&self, file_id: ObjectId) -> Result<DeleteResult, FileCenterError> {
-        Ok(self
+    /// Get the metadata of a file via an Object ID, without retrieving its data. If the file is temporary, it will still remain in the database.
+    pub async fn get_file_info_by_id(
+        &self,
+        id: ObjectId,
+    ) -> Result<Option<FileInfo>, FileCenterError> {
+        let mut options = FindOneOptions::default();
+        options.projection = Some(file_info_projection());
+
+        let file_item = self
             .collections
-            .files_chunks
-            .delete_many(
-                doc! {
-                    "file_id": file_id
-                },
-                None,
+            .files
+            .find_one(
+                Some(doc! {
+                    "_id": id,
+                    "detached": {
+                        "$ne": true
+                    }
+                }),
+                Some(options),
             )
-            .await?)
-    }
-}
-
-impl FileCenter {
-    async fn upload_from_stream(
-        &self,
-        file_id: ObjectId,
-        mut source: impl AsyncRead + Unpin,
-    ) -> Result<ObjectId, FileCenterError> {
-        let collection_files_chunks = &self.collections.files_chunks;
+            .await?;
 
-        let buffer_size = self.file_size_threshold as usize;
+        let file_item = match file_item {
+            Some(file_item) => {
+                self.resolve_file_reference(file_item, file_info_projection()).await?
+            },
+            None => None,
+        };
 
-        let mut buffer: Vec<u8> = vec![0u8; buffer_size];
+        match file_item {
+            Some(file_item) => {
+                let file_info = Self::create_file_info(file_item)?;
 
-        let mut n = 0i64;
+                if let Some(expire_at) = file_info.expire_at {
+                    if DateTime::now().gt(&expire_at) {
+                        return Ok(None);
+                    }
+                }
 
-        let mut inserted_id = None;
+                Ok(Some(file_info))
+            },
+            None => Ok(None),
+        }
+    }
 
-        loop {
-            let mut cc = 0;
-
-            // read to full
-            loop {
-                let c = match source.read(&mut buffer[cc..]).await {
-                    Ok(0) => break,
-                    Ok(c) => c,
-                    Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
-                    Err(e) => return Err(e.into()),
-                };
+    /// List the metadata of file items which satisfy the query. Temporary files will still remain in the database.
+    pub async fn find_file_infos(
+        &self,
+        query: FileQuery,
+    ) -> Result<impl Stream<Item = Result<FileInfo, FileCenterError>> + Unpin + Send, FileCenterError>
+    {
+        let mut conditions = vec![doc! {
+            "detached": {
+                "$ne": true
+            }
+        }];
 
-                cc += c;
+        let now = DateTime::now();
 
-                if cc == buffer_size {
-                    break;
+        match query.temporary {
+            Some(true) => conditions.push(doc! {
+                "expire_at": {
+                    "$gt": now
                 }
-            }
+            }),
+            Some(false) => conditions.push(doc! {
+                "expire_at": {
+                    "$exists": false
+                }
+            }),
+            None => conditions.push(doc! {
+                "$or": [
+                    {
+                        "expire_at": {
+                            "$exists": false
+                        }
+                    },
+                    {
+                        "expire_at": {
+                            "$gt": now
+                        }
+                    }
+                ]
+            }),
+        }
 
-            // read nothing
-            if cc == 0 {
-                break;
-            }
+        if let Some(mime_type) = query.mime_type {
+            conditions.push(doc! {
+                "mime_type": mime_type.as_ref()
+            });
+        }
 
-            let chunk = &buffer[..cc];
+        if let Some(mime_type_prefix) = query.mime_type_prefix {
+            conditions.push(doc! {
+                "mime_type": Regex {
+                    pattern: format!("^{}", escape_regex(&mime_type_prefix)),
+                    options: String::new(),
+                }
+            });
+        }
 
-            let result = collection_files_chunks
-                .insert_one(chunk_document(file_id, n, chunk.to_vec()), None)
-                .await?;
+        if let Some(file_name_pattern) = query.file_name_pattern {
+            conditions.push(doc! {
+                "file_name": Regex {
+                    pattern: file_name_pattern,
+                    options: String::new(),
+                }
+            });
+        }
 
-            inserted_id = Some(match result.inserted_id.as_object_id() {
-                Some(id) => id,
-                None => {
-                    return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
-                },
+        if let Some(min_file_size) = query.min_file_size {
+            conditions.push(doc! {
+                "file_size": {
+                    "$gte": min_file_size as i64
+                }
             });
+        }
 
-            n += 1;
+        if let Some(max_file_size) = query.max_file_size {
+            conditions.push(doc! {
+                "file_size": {
+                    "$lte": max_file_size as i64
+                }
+            });
         }
 
-        match inserted_id {
-            Some(inserted_id) => Ok(inserted_id),
-            None => {
-                let result = collection_files_chunks
-                    .insert_one(chunk_document(file_id, 0, Vec::new()), None)
-                    .await?;
+        if let Some(created_after) = query.created_after {
+            conditions.push(doc! {
+                "create_time": {
+                    "$gte": created_after
+                }
+            });
+        }
 
-                match result.inserted_id.as_object_id() {
-                    Some(id) => Ok(id),
-                    None => Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType)),
+        if let Some(created_before) = query.created_before {
+            conditions.push(doc! {
+                "create_time": {
+                    "$lt": created_before
                 }
-            },
+            });
         }
-    }
 
-    /// Input a file to the file center via a file path.
-    pub async fn put_file_by_path<P: AsRef<Path>, S: Into<String>>(
-        &self,
-        file_path: P,
-        file_name: Option<S>,
-        mime_type: Option<Mime>,
-    ) -> Result<ObjectId, FileCenterError> {
-        let file_path = file_path.as_ref();
+        if let Some(metadata) = query.metadata {
+            for (key, value) in metadata {
+                conditions.push(doc! {
+                    format!("metadata.{}", key): value
+                });
+            }
+        }
 
-        let (hash_1, hash_2, hash_3, hash_4) = get_hash_by_path(file_path).await?;
+        if let Some(after) = query.after {
+            conditions.push(doc! {
+                "_id": {
+                    "$gt": after
+                }
+            });
+        }
 
-        let mut options = FindOneAndUpdateOptions::default();
-        options.return_document = Some(ReturnDocument::After);
-        options.projection = Some(file_exist_projection());
+        let mut pipeline = vec![
+            doc! {
+                "$match": {
+                    "$and": conditions
+                }
+            },
+            doc! {
+                "$sort": {
+                    "_id": 1
+                }
+            },
+        ];
 
-        let result = self
-            .collections
-            .files
-            .find_one_and_update(
-                doc! {
-                   "hash_1": hash_1,
-                   "hash_2": hash_2,
-                   "hash_3": hash_3,
-                   "hash_4": hash_4,
-                },
-                doc! {
-                    "$inc": {
-                        "count": 1
-                    }
+        if let Some(limit) = query.limit {
+            pipeline.push(doc! {
+                "$limit": limit as i64
+            });
+        }
+
+        // the reference count of a reference is the count of its content
+        pipeline.push(doc! {
+            "$lookup": {
+                "from": self.collections.files.name(),
+                "localField": "content_id",
+                "foreignField": "_id",
+                "as": "content"
+            }
+        });
+        pipeline.push(doc! {
+            "$addFields": {
+                "count": {
+                    "$ifNull": [
+                        {
+                            "$arrayElemAt": ["$content.count", 0]
+                        },
+                        "$count"
+                    ]
                 },
-                Some(options),
-            )
-            .await?;
+                "content_encoding": {
+                    "$ifNull": [
+                        {
+                            "$arrayElemAt": ["$content.content_encoding", 0]
+                        },
+                        "$content_encoding"
+                    ]
+                }
+            }
+        });
+        pipeline.push(doc! {
+            "$project": file_info_projection()
+        });
 
-        match result {
-            Some(result) => Ok(result.get_object_id("_id")?),
-            None => {
-                let file_name = match file_name {
-                    Some(file_name) => file_name.into(),
-                    None => file_path.file_name().unwrap().to_str().unwrap().to_string(),
-                };
+        let cursor = self.collections.files.aggregate(pipeline, None).await?;
 
-                let mut file = File::open(file_path).await?;
+        Ok(cursor.map(|item| Self::create_file_info(item?)))
+    }
 
-                let metadata = file.metadata().await?;
+    /// Get the file item via an Object ID.
+    pub async fn get_file_item_by_id(
+        &self,
+        id: ObjectId,
+    ) -> Result<Option<FileItem>, FileCenterError> {
+        match self.find_file_document(id, file_item_projection()).await? {
+            Some(file_item) => {
+                let file_item = self.create_file_item(file_item).await?;
 
-                let file_size = metadata.len();
+                Ok(Some(file_item))
+            },
+            None => Ok(None),
+        }
+    }
 
-                let file_id = ObjectId::new();
+    /// Get a byte range of the file data via an Object ID. The range is clamped to the file size. If the file is chunked, only the chunks which overlap the range are retrieved. If the file is temporary, it will be removed from the database just like `get_file_item_by_id`.
+    pub async fn get_file_range_by_id<R: RangeBounds<u64>>(
+        &self,
+        id: ObjectId,
+        range: R,
+    ) -> Result<Option<FileData>, FileCenterError> {
+        let mut document = match self.find_file_document(id, file_range_projection()).await? {
+            Some(document) => document,
+            None => return Ok(None),
+        };
 
-                let mut file_item_raw = doc! {
-                    "_id": file_id,
-                    "hash_1": hash_1,
-                    "hash_2": hash_2,
-                    "hash_3": hash_3,
-                    "hash_4": hash_4,
-                    "file_size": file_size as i64,
-                    "file_name": file_name,
-                    "count": 1i32
-                };
+        let content_id = document.get_object_id("content_id").unwrap_or(id);
 
-                if file_size > self.file_size_threshold as u64 {
-                    let chunk_id = match self.upload_from_stream(file_id, file).await {
-                        Ok(id) => id,
-                        Err(err) => {
-                            if self.delete_file_chunks(file_id).await.is_err() {
-                                // do nothing
-                            }
+        let file_size = document.get_i64("file_size")? as u64;
 
-                            return Err(err);
-                        },
-                    };
+        let decryption_key = self.get_decryption_key(&document)?;
+        let content_encoding = get_content_encoding(&document)?;
 
-                    file_item_raw.insert("chunk_id", chunk_id);
-                } else {
-                    let mut file_data = Vec::with_capacity(file_size as usize);
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        }
+        .min(file_size);
 
-                    file.read_to_end(&mut file_data).await?;
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => file_size,
+        }
+        .min(file_size);
 
-                    file_item_raw.insert(
-                        "file_data",
-                        Bson::Binary(Binary {
-                            subtype: BinarySubtype::Generic,
-                            bytes:   file_data,
-                        }),
-                    );
+        if start >= end {
+            return Ok(Some(FileData::Buffer(Vec::new())));
+        }
 
-                    drop(file);
-                }
+        match document.remove("file_data") {
+            Some(file_data) => match file_data {
+                Bson::Binary(b) => {
+                    let bytes = match decryption_key {
+                        Some(decryption_key) => decryption_key
+                            .decrypt(&b.bytes, &file_data_associated_data(content_id))?,
+                        None => b.bytes,
+                    };
 
-                let mime_type = match mime_type {
-                    Some(mime_type) => mime_type,
-                    None => get_mime_by_path(file_path),
-                };
+                    let mut bytes = match content_encoding {
+                        Some(content_encoding) => content_encoding.decode(&bytes)?,
+                        None => bytes,
+                    };
 
-                file_item_raw.insert("mime_type", mime_type.as_ref());
+                    bytes.truncate(end as usize);
+                    bytes.drain(..start as usize);
 
-                file_item_raw.insert("create_time", DateTime::now());
+                    Ok(Some(FileData::Buffer(bytes)))
+                },
+                _ => Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType)),
+            },
+            None => {
+                let chunk_size = match document.get("chunk_size") {
+                    Some(chunk_size) => match chunk_size.as_i32() {
+                        Some(chunk_size) if chunk_size > 0 => chunk_size as u64,
+                        _ => {
+                            return Err(FileCenterError::DocumentError(
+                                ValueAccessError::UnexpectedType,
+                            ));
+                        },
+                    },
+                    None => match self.find_first_chunk_size(content_id).await? {
+                        Some(chunk_size) => chunk_size,
+                        None => return Ok(Some(FileData::Buffer(Vec::new()))),
+                    },
+                };
 
-                self.collections.files.insert_one(file_item_raw, None).await?;
+                let stream = self
+                    .open_download_range_stream(
+                        content_id,
+                        chunk_size,
+                        decryption_key,
+                        content_encoding,
+                        start,
+                        end,
+                    )
+                    .await?;
 
-                Ok(file_id)
+                Ok(Some(FileData::Stream(stream)))
             },
         }
     }
 
-    /// Temporarily input a file to the file center via a file path.
-    pub async fn put_file_by_path_temporarily<P: AsRef<Path>, S: Into<String>>(
+    /// Replace the user-defined metadata of a file item. Set `metadata` to `None` to remove it. Returns `false` if the file item does not exist.
+    pub async fn set_file_metadata(
         &self,
-        file_path: P,
-        file_name: Option<S>,
-        mime_type: Option<Mime>,
-    ) -> Result<ObjectId, FileCenterError> {
-        let file_path = file_path.as_ref();
-
-        let file_name = match file_name {
-            Some(file_name) => file_name.into(),
-            None => file_path.file_name().unwrap().to_str().unwrap().to_string(),
+        id: ObjectId,
+        metadata: Option<Document>,
+    ) -> Result<bool, FileCenterError> {
+        let update = match metadata {
+            Some(metadata) => doc! {
+                "$set": {
+                    "metadata": metadata
+                }
+            },
+            None => doc! {
+                "$unset": {
+                    "metadata": ""
+                }
+            },
         };
 
-        let mut file = File::open(file_path).await?;
+        let result = self
+            .collections
+            .files
+            .update_one(
+                doc! {
+                    "_id": id,
+                    "detached": {
+                        "$ne": true
+                    }
+                },
+                update,
+                None,
+            )
+            .await?;
 
-        let metadata = file.metadata().await?;
+        Ok(result.matched_count > 0)
+    }
 
-        let file_size = metadata.len();
+    /// Remove a file item via an Object ID.
+    pub async fn delete_file_item_by_id(
+        &self,
+        file_id: ObjectId,
+    ) -> Result<Option<u64>, FileCenterError> {
+        let mut session = self.start_session().await?;
 
-        let file_id = ObjectId::new();
+        loop {
+            let result = self.delete_file_item_by_id_with_session(file_id, &mut session).await;
 
-        let mut file_item_raw = doc! {
-            "_id": file_id,
-            "file_size": file_size as i64,
-            "file_name": file_name,
-            "count": 1i32
-        };
+            if let Some(result) = session.end_or_restart(result).await {
+                return result;
+            }
+        }
+    }
 
-        let is_stream = file_size > self.file_size_threshold as u64;
+    async fn delete_file_item_by_id_with_session(
+        &self,
+        file_id: ObjectId,
+        session: &mut Session,
+    ) -> Result<Option<u64>, FileCenterError> {
+        let mut options = FindOneAndDeleteOptions::default();
+        options.projection = Some(file_item_delete_projection());
 
-        if is_stream {
-            let chunk_id = match self.upload_from_stream(file_id, file).await {
-                Ok(id) => id,
-                Err(err) => {
-                    if self.delete_file_chunks(file_id).await.is_err() {
-                        // do nothing
+        let reference = session
+            .find_one_and_delete(
+                &self.collections.files,
+                doc! {
+                    "_id": file_id,
+                    "content_id": {
+                        "$exists": true
                     }
-
-                    return Err(err);
                 },
-            };
+                options,
+            )
+            .await?;
 
-            file_item_raw.insert("chunk_id", chunk_id);
-        } else {
-            let mut file_data = Vec::with_capacity(file_size as usize);
+        match reference {
+            Some(reference) => {
+                let content_id = reference.get_object_id("content_id")?;
+                let file_size = reference.get_i64("file_size")? as u64;
 
-            file.read_to_end(&mut file_data).await?;
+                self.remove_file_item_usage(&reference, session).await?;
 
-            file_item_raw.insert(
-                "file_data",
-                Bson::Binary(Binary {
-                    subtype: BinarySubtype::Generic, bytes: file_data
-                }),
-            );
+                self.decrease_file_count(content_id, false, session).await?;
 
-            drop(file);
+                Ok(Some(file_size))
+            },
+            None => self.decrease_file_count(file_id, true, session).await,
         }
+    }
 
-        let mime_type = match mime_type {
-            Some(mime_type) => mime_type,
-            None => get_mime_by_path(file_path),
+    /// Decrease the reference count of a file content, and remove it if it is no longer used. If `detach` is `true`, the file content can no longer be accessed via its own ID.
+    async fn decrease_file_count(
+        &self,
+        file_id: ObjectId,
+        detach: bool,
+        session: &mut Session,
+    ) -> Result<Option<u64>, FileCenterError> {
+        let collection_files = &self.collections.files;
+
+        let mut options = FindOneAndUpdateOptions::default();
+        options.return_document = Some(ReturnDocument::After);
+        options.projection = Some(file_item_delete_projection());
+
+        let (filter, update) = if detach {
+            (
+                doc! {
+                    "_id": file_id,
+                    "content_id": {
+                        "$exists": false
+                    },
+                    "detached": {
+                        "$ne": true
+                    }
+                },
+                doc! {
+                    "$inc": {
+                        "count": -1
+                    },
+                    "$set": {
+                        "detached": true
+                    }
+                },
+            )
+        } else {
+            (
+                doc! {
+                    "_id": file_id
+                },
+                doc! {
+                    "$inc": {
+                        "count": -1
+                    }
+                },
+            )
         };
 
-        file_item_raw.insert("mime_type", mime_type.as_ref());
+        let result = session.find_one_and_update(collection_files, filter, update, options).await?;
 
-        let now = DateTime::now();
+        match result {
+            Some(result) => {
+                let count = result.get_i32("count")?;
+                let file_size = result.get_i64("file_size")? as u64;
 
-        let expire = DateTime::from_millis(now.timestamp_millis() + TEMPORARY_LIFE_TIME);
-        let expire_chunks =
-            DateTime::from_millis(now.timestamp_millis() + TEMPORARY_CHUNK_LIFE_TIME);
+                // the file item of the content is no longer counted for its owner once it is detached, and the references are counted for their own owners
+                if detach || (count <= 0 && !result.get_bool("detached").unwrap_or(false)) {
+                    if let Ok(owner) = result.get_str("owner") {
+                        if !result.contains_key("expire_at") {
+                            self.add_usage(Some(owner), -(file_size as i64), -1, session).await?;
+                        }
+                    }
+                }
 
-        file_item_raw.insert("create_time", now);
+                if count <= 0 {
+                    session
+                        .delete_one(collection_files, doc! {
+                            "_id": file_id
+                        })
+                        .await?;
 
-        file_item_raw.insert("expire_at", expire);
+                    if result.get("chunk_id").is_some()
+                        && self.delete_file_chunks(file_id, session).await.is_err()
+                    {
+                    }
+                }
 
-        if is_stream {
-            self.collections
-                .files_chunks
-                .update_many(
+                Ok(Some(file_size))
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+impl FileCenter {
+    #[inline]
+    pub(crate) async fn delete_file_chunks(
+        &self,
+        file_id: ObjectId,
+        session: &mut Session,
+    ) -> Result<DeleteResult, FileCenterError> {
+        Ok(session
+            .delete_many(&self.collections.files_chunks, doc! {
+                "file_id": file_id
+            })
+            .await?)
+    }
+}
+
+impl FileCenter {
+    /// Add a reference, which has its own file name, MIME type and metadata, to an existing file content whose reference count has already been increased.
+    async fn insert_file_reference(
+        &self,
+        content: Document,
+        file_name: String,
+        mime_type: Mime,
+        metadata: Option<Document>,
+        owner: Option<String>,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        let content_id = content.get_object_id("_id")?;
+        let file_size = content.get_i64("file_size")?;
+
+        let file_id = ObjectId::new();
+
+        let mut file_item_raw = doc! {
+            "_id": file_id,
+            "content_id": content_id,
+            "file_size": file_size,
+            "file_name": file_name,
+            "mime_type": mime_type.as_ref(),
+            "create_time": DateTime::now(),
+        };
+
+        if let Some(metadata) = metadata {
+            file_item_raw.insert("metadata", metadata);
+        }
+
+        insert_owner(&mut file_item_raw, owner.clone());
+
+        if let Err(err) = session.insert_one(&self.collections.files, file_item_raw).await {
+            if session
+                .update_one(
+                    &self.collections.files,
                     doc! {
-                        "file_id": file_id
+                        "_id": content_id
                     },
                     doc! {
-                        "$set": {
-                            "expire_at": expire_chunks
+                        "$inc": {
+                            "count": -1
                         }
                     },
-                    None,
                 )
-                .await?;
+                .await
+                .is_err()
+            {
+                // do nothing
+            }
+
+            return Err(err.into());
         }
 
-        self.collections.files.insert_one(file_item_raw, None).await?;
+        self.add_usage(owner.as_deref(), file_size, 1, session).await?;
 
         Ok(file_id)
     }
-}
 
-impl FileCenter {
-    async fn upload_from_buffer(
+    async fn upload_from_stream<R: AsyncRead + Unpin>(
         &self,
-        file_id: ObjectId,
-        source: &[u8],
+        session: &mut Session,
+        reader: &mut ChunkReader<'_, R>,
     ) -> Result<ObjectId, FileCenterError> {
         let collection_files_chunks = &self.collections.files_chunks;
 
-        let chunk_size = self.file_size_threshold as usize;
+        let options = self.chunk_batch_options;
 
         let mut inserted_id = None;
 
-        for (n, chunk) in source.chunks(chunk_size).enumerate() {
-            let result = collection_files_chunks
-                .insert_one(chunk_document(file_id, n as i64, chunk.to_vec()), None)
-                .await?;
+        let mut batch = reader.read_batch(&options).await?;
 
-            inserted_id = Some(match result.inserted_id.as_object_id() {
-                Some(id) => id,
-                None => {
-                    return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
-                },
-            });
+        while !batch.is_empty() {
+            batch = if options.pipelined {
+                let (result, next_batch) = tokio::join!(
+                    insert_chunks(collection_files_chunks, session, batch),
+                    reader.read_batch(&options)
+                );
+
+                inserted_id = Some(result?);
+
+                next_batch?
+            } else {
+                inserted_id = Some(insert_chunks(collection_files_chunks, session, batch).await?);
+
+                reader.read_batch(&options).await?
+            };
         }
 
         match inserted_id {
             Some(inserted_id) => Ok(inserted_id),
             None => {
-                let result = collection_files_chunks
-                    .insert_one(chunk_document(file_id, 0, Vec::new()), None)
-                    .await?;
-
-                match result.inserted_id.as_object_id() {
-                    Some(id) => Ok(id),
-                    None => Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType)),
-                }
+                insert_chunk(collection_files_chunks, session, reader.get_file_id(), 0, Vec::new())
+                    .await
             },
         }
     }
 
-    /// Input a file to the file center via a buffer.
-    pub async fn put_file_by_buffer<B: AsRef<[u8]> + Into<Vec<u8>>, S: Into<String>>(
+    /// Input a file to the file center via a file path.
+    #[inline]
+    pub async fn put_file_by_path<P: AsRef<Path>, S: Into<String>>(
         &self,
-        buffer: B,
-        file_name: S,
+        file_path: P,
+        file_name: Option<S>,
+        mime_type: Option<Mime>,
+    ) -> Result<ObjectId, FileCenterError> {
+        self.put_file_by_path_with_options(
+            file_path,
+            file_name,
+            mime_type,
+            UploadOptions::default(),
+        )
+        .await
+    }
+
+    /// Input a file to the file center via a file path with extra options.
+    pub async fn put_file_by_path_with_options<P: AsRef<Path>, S: Into<String>>(
+        &self,
+        file_path: P,
+        file_name: Option<S>,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+    ) -> Result<ObjectId, FileCenterError> {
+        let file_name: Option<String> = file_name.map(Into::into);
+
+        let mut session = self.start_session().await?;
+
+        loop {
+            let result = self
+                .put_file_by_path_with_options_inner(
+                    &file_path,
+                    file_name.clone(),
+                    mime_type.clone(),
+                    upload_options.clone(),
+                    &mut session,
+                )
+                .await;
+
+            if let Some(result) = session.end_or_restart(result).await {
+                return result;
+            }
+        }
+    }
+
+    async fn put_file_by_path_with_options_inner<P: AsRef<Path>, S: Into<String>>(
+        &self,
+        file_path: P,
+        file_name: Option<S>,
         mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+        session: &mut Session,
     ) -> Result<ObjectId, FileCenterError> {
-        let (hash_1, hash_2, hash_3, hash_4) = get_hash_by_buffer(buffer.as_ref());
+        let file_path = file_path.as_ref();
+
+        self.check_quota(
+            upload_options.owner.as_deref(),
+            tokio::fs::metadata(file_path).await?.len(),
+        )
+        .await?;
+
+        let file_name = match file_name {
+            Some(file_name) => file_name.into(),
+            None => file_path.file_name().unwrap().to_str().unwrap().to_string(),
+        };
+
+        let threshold = self.get_inline_threshold(&file_name, &upload_options)?;
+
+        let (hash_1, hash_2, hash_3, hash_4) = get_hash_by_path(file_path).await?;
+
+        let mime_type = match mime_type {
+            Some(mime_type) => mime_type,
+            None => get_mime_by_path(file_path),
+        };
+
+        let encoder = self.get_data_encoder(&upload_options, &mime_type);
 
         let mut options = FindOneAndUpdateOptions::default();
         options.return_document = Some(ReturnDocument::After);
-        options.projection = Some(file_exist_projection());
+        options.projection = Some(file_reference_projection());
 
-        let result = self
-            .collections
-            .files
+        let result = session
             .find_one_and_update(
+                &self.collections.files,
                 doc! {
                    "hash_1": hash_1,
                    "hash_2": hash_2,
                    "hash_3": hash_3,
                    "hash_4": hash_4,
+                   "encryption_key_id": encoder.get_encryption_key_id(),
                 },
                 doc! {
                     "$inc": {
                         "count": 1
                     }
                 },
-                Some(options),
+                options,
             )
             .await?;
 
         match result {
-            Some(result) => Ok(result.get_object_id("_id")?),
+            Some(result) => {
+                self.insert_file_reference(
+                    result,
+                    file_name,
+                    mime_type,
+                    upload_options.metadata,
+                    upload_options.owner,
+                    session,
+                )
+                .await
+            },
             None => {
-                let buffer = buffer.into();
-                let file_name = file_name.into();
+                let mut file = File::open(file_path).await?;
 
-                let file_size = buffer.len();
+                let metadata = file.metadata().await?;
+
+                let file_size = metadata.len();
 
                 let file_id = ObjectId::new();
 
@@ -1124,11 +2405,23 @@ impl FileCenter {
                     "count": 1i32
                 };
 
-                if file_size > self.file_size_threshold as usize {
-                    let chunk_id = match self.upload_from_buffer(file_id, &buffer).await {
+                if file_size > threshold {
+                    let chunk_id = match self
+                        .upload_from_stream(
+                            session,
+                            &mut ChunkReader::new(
+                                file,
+                                file_id,
+                                self.chunk_size as usize,
+                                &encoder,
+                                None,
+                            ),
+                        )
+                        .await
+                    {
                         Ok(id) => id,
                         Err(err) => {
-                            if self.delete_file_chunks(file_id).await.is_err() {
+                            if self.delete_file_chunks(file_id, session).await.is_err() {
                                 // do nothing
                             }
 
@@ -1137,41 +2430,118 @@ impl FileCenter {
                     };
 
                     file_item_raw.insert("chunk_id", chunk_id);
+                    file_item_raw.insert("chunk_size", self.chunk_size as i32);
 
-                    drop(buffer);
+                    encoder.insert_chunk_fields(&mut file_item_raw);
                 } else {
-                    file_item_raw.insert(
-                        "file_data",
-                        Bson::Binary(Binary {
-                            subtype: BinarySubtype::Generic, bytes: buffer
-                        }),
-                    );
-                }
+                    let mut file_data = Vec::with_capacity(file_size as usize);
+
+                    file.read_to_end(&mut file_data).await?;
+
+                    encoder.insert_file_data(&mut file_item_raw, file_id, file_data)?;
 
-                let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
+                    drop(file);
+                }
 
                 file_item_raw.insert("mime_type", mime_type.as_ref());
 
+                if let Some(metadata) = upload_options.metadata {
+                    file_item_raw.insert("metadata", metadata);
+                }
+
+                insert_owner(&mut file_item_raw, upload_options.owner.clone());
+
                 file_item_raw.insert("create_time", DateTime::now());
 
-                self.collections.files.insert_one(file_item_raw, None).await?;
+                session.insert_one(&self.collections.files, file_item_raw).await?;
+
+                self.add_usage(upload_options.owner.as_deref(), file_size as i64, 1, session)
+                    .await?;
 
                 Ok(file_id)
             },
         }
     }
 
-    /// Temporarily input a file to the file center via a buffer.
-    pub async fn put_file_by_buffer_temporarily<B: AsRef<[u8]> + Into<Vec<u8>>, S: Into<String>>(
+    /// Temporarily input a file to the file center via a file path.
+    #[inline]
+    pub async fn put_file_by_path_temporarily<P: AsRef<Path>, S: Into<String>>(
         &self,
-        buffer: B,
-        file_name: S,
+        file_path: P,
+        file_name: Option<S>,
         mime_type: Option<Mime>,
     ) -> Result<ObjectId, FileCenterError> {
-        let buffer = buffer.into();
-        let file_name = file_name.into();
-
-        let file_size = buffer.len();
+        self.put_file_by_path_temporarily_with_options(
+            file_path,
+            file_name,
+            mime_type,
+            UploadOptions::default(),
+        )
+        .await
+    }
+
+    /// Temporarily input a file to the file center via a file path with extra options.
+    pub async fn put_file_by_path_temporarily_with_options<P: AsRef<Path>, S: Into<String>>(
+        &self,
+        file_path: P,
+        file_name: Option<S>,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+    ) -> Result<ObjectId, FileCenterError> {
+        let file_name: Option<String> = file_name.map(Into::into);
+
+        let mut session = self.start_session().await?;
+
+        loop {
+            let result = self
+                .put_file_by_path_temporarily_with_options_inner(
+                    &file_path,
+                    file_name.clone(),
+                    mime_type.clone(),
+                    upload_options.clone(),
+                    &mut session,
+                )
+                .await;
+
+            if let Some(result) = session.end_or_restart(result).await {
+                return result;
+            }
+        }
+    }
+
+    async fn put_file_by_path_temporarily_with_options_inner<P: AsRef<Path>, S: Into<String>>(
+        &self,
+        file_path: P,
+        file_name: Option<S>,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        Self::check_temporary_expiration(upload_options.expiration)?;
+
+        let file_path = file_path.as_ref();
+
+        let file_name = match file_name {
+            Some(file_name) => file_name.into(),
+            None => file_path.file_name().unwrap().to_str().unwrap().to_string(),
+        };
+
+        let mime_type = match mime_type {
+            Some(mime_type) => mime_type,
+            None => get_mime_by_path(file_path),
+        };
+
+        let threshold = self.get_inline_threshold(&file_name, &upload_options)?;
+
+        let encoder = self.get_data_encoder(&upload_options, &mime_type);
+
+        let mut file = File::open(file_path).await?;
+
+        let metadata = file.metadata().await?;
+
+        let file_size = metadata.len();
+
+        self.check_quota(upload_options.owner.as_deref(), file_size).await?;
 
         let file_id = ObjectId::new();
 
@@ -1182,13 +2552,19 @@ impl FileCenter {
             "count": 1i32
         };
 
-        let is_stream = file_size > self.file_size_threshold as usize;
+        let is_stream = file_size > threshold;
 
         if is_stream {
-            let chunk_id = match self.upload_from_buffer(file_id, &buffer).await {
+            let chunk_id = match self
+                .upload_from_stream(
+                    session,
+                    &mut ChunkReader::new(file, file_id, self.chunk_size as usize, &encoder, None),
+                )
+                .await
+            {
                 Ok(id) => id,
                 Err(err) => {
-                    if self.delete_file_chunks(file_id).await.is_err() {
+                    if self.delete_file_chunks(file_id, session).await.is_err() {
                         // do nothing
                     }
 
@@ -1197,35 +2573,44 @@ impl FileCenter {
             };
 
             file_item_raw.insert("chunk_id", chunk_id);
+            file_item_raw.insert("chunk_size", self.chunk_size as i32);
 
-            drop(buffer);
+            encoder.insert_chunk_fields(&mut file_item_raw);
         } else {
-            file_item_raw.insert(
-                "file_data",
-                Bson::Binary(Binary {
-                    subtype: BinarySubtype::Generic, bytes: buffer
-                }),
-            );
-        }
+            let mut file_data = Vec::with_capacity(file_size as usize);
 
-        let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
+            file.read_to_end(&mut file_data).await?;
+
+            encoder.insert_file_data(&mut file_item_raw, file_id, file_data)?;
+
+            drop(file);
+        }
 
         file_item_raw.insert("mime_type", mime_type.as_ref());
 
+        if let Some(metadata) = upload_options.metadata {
+            file_item_raw.insert("metadata", metadata);
+        }
+
+        insert_owner(&mut file_item_raw, upload_options.owner);
+
         let now = DateTime::now();
 
-        let expire = DateTime::from_millis(now.timestamp_millis() + TEMPORARY_LIFE_TIME);
-        let expire_chunks =
-            DateTime::from_millis(now.timestamp_millis() + TEMPORARY_CHUNK_LIFE_TIME);
+        let expire = self.get_temporary_expiration(now, upload_options.expiration);
+        let expire_chunks = DateTime::from_millis(
+            expire.timestamp_millis().saturating_add(TEMPORARY_CHUNK_EXTRA_LIFE_TIME),
+        );
 
         file_item_raw.insert("create_time", now);
 
         file_item_raw.insert("expire_at", expire);
 
+        insert_read_limit(&mut file_item_raw, upload_options.read_limit);
+
         if is_stream {
-            self.collections
-                .files_chunks
+            session
                 .update_many(
+                    &self.collections.files_chunks,
                     doc! {
                         "file_id": file_id
                     },
@@ -1234,286 +2619,751 @@ impl FileCenter {
                             "expire_at": expire_chunks
                         }
                     },
-                    None,
                 )
                 .await?;
         }
 
-        self.collections.files.insert_one(file_item_raw, None).await?;
+        session.insert_one(&self.collections.files, file_item_raw).await?;
 
         Ok(file_id)
     }
 }
 
 impl FileCenter {
-    async fn upload_from_stream_and_hash(
+    /// Input a file to the file center via a buffer.
+    #[inline]
+    pub async fn put_file_by_buffer<B: AsRef<[u8]> + Into<Vec<u8>>, S: Into<String>>(
         &self,
-        file_id: ObjectId,
-        mut first_chunk_plus_one: Vec<u8>,
-        mut source: impl AsyncRead + Unpin,
-    ) -> Result<(ObjectId, i64, (i64, i64, i64, i64)), FileCenterError> {
-        let collection_files_chunks = &self.collections.files_chunks;
-
-        let buffer_size = self.file_size_threshold as usize;
-        let mut buffer: Vec<u8> = vec![0u8; buffer_size];
-
-        buffer[0] = first_chunk_plus_one[buffer_size];
-
-        let mut hasher = Hasher::new();
-
-        hasher.update(&first_chunk_plus_one[..buffer_size]);
-
-        unsafe {
-            first_chunk_plus_one.set_len(buffer_size);
-        }
-
-        let result = collection_files_chunks
-            .insert_one(chunk_document(file_id, 0, first_chunk_plus_one), None)
-            .await?;
+        buffer: B,
+        file_name: S,
+        mime_type: Option<Mime>,
+    ) -> Result<ObjectId, FileCenterError> {
+        self.put_file_by_buffer_with_options(buffer, file_name, mime_type, UploadOptions::default())
+            .await
+    }
 
-        let mut inserted_id = match result.inserted_id.as_object_id() {
-            Some(id) => id,
-            None => {
-                return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
-            },
-        };
+    /// Input a file to the file center via a buffer with extra options.
+    pub async fn put_file_by_buffer_with_options<
+        B: AsRef<[u8]> + Into<Vec<u8>>,
+        S: Into<String>,
+    >(
+        &self,
+        buffer: B,
+        file_name: S,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+    ) -> Result<ObjectId, FileCenterError> {
+        let file_name = file_name.into();
 
-        let mut n = 1i64;
-        let mut cc = 1;
-        let mut file_size = buffer_size as i64;
+        let mut session = self.start_session().await?;
 
         loop {
-            // read to full
-            loop {
-                let c = match source.read(&mut buffer[cc..]).await {
-                    Ok(0) => break,
-                    Ok(c) => c,
-                    Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
-                    Err(e) => return Err(e.into()),
-                };
-
-                cc += c;
-
-                if cc == buffer_size {
-                    break;
-                }
-            }
+            let result = self
+                .put_file_by_buffer_with_options_inner(
+                    buffer.as_ref(),
+                    file_name.clone(),
+                    mime_type.clone(),
+                    upload_options.clone(),
+                    &mut session,
+                )
+                .await;
 
-            // read nothing
-            if cc == 0 {
-                break;
+            if let Some(result) = session.end_or_restart(result).await {
+                return result;
             }
-
-            let chunk = &buffer[..cc];
-
-            hasher.update(chunk);
-
-            let result = collection_files_chunks
-                .insert_one(chunk_document(file_id, n, chunk.to_vec()), None)
-                .await?;
-
-            inserted_id = match result.inserted_id.as_object_id() {
-                Some(id) => id,
-                None => {
-                    return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
-                },
-            };
-
-            n += 1;
-            file_size += cc as i64;
-
-            cc = 0;
         }
-
-        let hash = separate_hash(&hasher.finalize());
-
-        Ok((inserted_id, file_size, hash))
     }
 
-    async fn upload_from_stream_and_no_hash(
+    async fn put_file_by_buffer_with_options_inner(
         &self,
-        file_id: ObjectId,
-        mut first_chunk_plus_one: Vec<u8>,
-        mut source: impl AsyncRead + Unpin,
-    ) -> Result<(ObjectId, i64), FileCenterError> {
-        let collection_files_chunks = &self.collections.files_chunks;
+        buffer: &[u8],
+        file_name: String,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        let threshold = self.get_inline_threshold(&file_name, &upload_options)?;
 
-        let buffer_size = self.file_size_threshold as usize;
-        let mut buffer: Vec<u8> = vec![0u8; buffer_size];
+        self.check_quota(upload_options.owner.as_deref(), buffer.len() as u64).await?;
 
-        buffer[0] = first_chunk_plus_one[buffer_size];
+        let (hash_1, hash_2, hash_3, hash_4) = get_hash_by_buffer(buffer);
 
-        unsafe {
-            first_chunk_plus_one.set_len(buffer_size);
-        }
+        let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
+
+        let encoder = self.get_data_encoder(&upload_options, &mime_type);
+
+        let mut options = FindOneAndUpdateOptions::default();
+        options.return_document = Some(ReturnDocument::After);
+        options.projection = Some(file_reference_projection());
 
-        let result = collection_files_chunks
-            .insert_one(chunk_document(file_id, 0, first_chunk_plus_one), None)
+        let result = session
+            .find_one_and_update(
+                &self.collections.files,
+                doc! {
+                   "hash_1": hash_1,
+                   "hash_2": hash_2,
+                   "hash_3": hash_3,
+                   "hash_4": hash_4,
+                   "encryption_key_id": encoder.get_encryption_key_id(),
+                },
+                doc! {
+                    "$inc": {
+                        "count": 1
+                    }
+                },
+                options,
+            )
             .await?;
 
-        let mut inserted_id = match result.inserted_id.as_object_id() {
-            Some(id) => id,
-            None => {
-                return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
+        match result {
+            Some(result) => {
+                self.insert_file_reference(
+                    result,
+                    file_name,
+                    mime_type,
+                    upload_options.metadata,
+                    upload_options.owner,
+                    session,
+                )
+                .await
             },
-        };
+            None => {
+                let file_size = buffer.len();
 
-        let mut n = 1i64;
-        let mut cc = 1;
-        let mut file_size = buffer_size as i64;
+                let file_id = ObjectId::new();
 
-        loop {
-            // read to full
-            loop {
-                let c = match source.read(&mut buffer[cc..]).await {
-                    Ok(0) => break,
-                    Ok(c) => c,
-                    Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
-                    Err(e) => return Err(e.into()),
+                let mut file_item_raw = doc! {
+                    "_id": file_id,
+                    "hash_1": hash_1,
+                    "hash_2": hash_2,
+                    "hash_3": hash_3,
+                    "hash_4": hash_4,
+                    "file_size": file_size as i64,
+                    "file_name": file_name,
+                    "count": 1i32
                 };
 
-                cc += c;
+                if file_size as u64 > threshold {
+                    let chunk_id = match self
+                        .upload_from_stream(
+                            session,
+                            &mut ChunkReader::new(
+                                buffer,
+                                file_id,
+                                self.chunk_size as usize,
+                                &encoder,
+                                None,
+                            ),
+                        )
+                        .await
+                    {
+                        Ok(id) => id,
+                        Err(err) => {
+                            if self.delete_file_chunks(file_id, session).await.is_err() {
+                                // do nothing
+                            }
+
+                            return Err(err);
+                        },
+                    };
 
-                if cc == buffer_size {
-                    break;
+                    file_item_raw.insert("chunk_id", chunk_id);
+                    file_item_raw.insert("chunk_size", self.chunk_size as i32);
+
+                    encoder.insert_chunk_fields(&mut file_item_raw);
+                } else {
+                    encoder.insert_file_data(&mut file_item_raw, file_id, buffer.to_vec())?;
                 }
-            }
 
-            // read nothing
-            if cc == 0 {
-                break;
-            }
+                file_item_raw.insert("mime_type", mime_type.as_ref());
 
-            let chunk = &buffer[..cc];
+                if let Some(metadata) = upload_options.metadata {
+                    file_item_raw.insert("metadata", metadata);
+                }
 
-            let result = collection_files_chunks
-                .insert_one(chunk_document(file_id, n, chunk.to_vec()), None)
-                .await?;
+                insert_owner(&mut file_item_raw, upload_options.owner.clone());
 
-            inserted_id = match result.inserted_id.as_object_id() {
-                Some(id) => id,
-                None => {
-                    return Err(FileCenterError::DocumentError(ValueAccessError::UnexpectedType));
-                },
-            };
+                file_item_raw.insert("create_time", DateTime::now());
 
-            n += 1;
-            file_size += cc as i64;
+                session.insert_one(&self.collections.files, file_item_raw).await?;
 
-            cc = 0;
-        }
+                self.add_usage(upload_options.owner.as_deref(), file_size as i64, 1, session)
+                    .await?;
 
-        Ok((inserted_id, file_size))
+                Ok(file_id)
+            },
+        }
     }
 
-    /// Input a file to the file center via a reader.
-    pub async fn put_file_by_reader<R: AsyncRead + Unpin, S: Into<String>>(
+    /// Temporarily input a file to the file center via a buffer.
+    #[inline]
+    pub async fn put_file_by_buffer_temporarily<B: AsRef<[u8]> + Into<Vec<u8>>, S: Into<String>>(
         &self,
-        mut reader: R,
+        buffer: B,
         file_name: S,
         mime_type: Option<Mime>,
     ) -> Result<ObjectId, FileCenterError> {
-        let buffer_size = self.file_size_threshold as usize + 1;
+        self.put_file_by_buffer_temporarily_with_options(
+            buffer,
+            file_name,
+            mime_type,
+            UploadOptions::default(),
+        )
+        .await
+    }
 
-        let mut file_data = vec![0u8; buffer_size];
+    /// Temporarily input a file to the file center via a buffer with extra options.
+    pub async fn put_file_by_buffer_temporarily_with_options<
+        B: AsRef<[u8]> + Into<Vec<u8>>,
+        S: Into<String>,
+    >(
+        &self,
+        buffer: B,
+        file_name: S,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+    ) -> Result<ObjectId, FileCenterError> {
+        let file_name = file_name.into();
 
-        let mut cc = 0;
+        let mut session = self.start_session().await?;
 
-        // read to full
         loop {
-            let c = match reader.read(&mut file_data[cc..]).await {
-                Ok(0) => break,
-                Ok(c) => c,
-                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
-                Err(e) => return Err(e.into()),
-            };
-
-            cc += c;
+            let result = self
+                .put_file_by_buffer_temporarily_with_options_inner(
+                    buffer.as_ref(),
+                    file_name.clone(),
+                    mime_type.clone(),
+                    upload_options.clone(),
+                    &mut session,
+                )
+                .await;
 
-            if cc == buffer_size {
-                break;
+            if let Some(result) = session.end_or_restart(result).await {
+                return result;
             }
         }
+    }
+
+    async fn put_file_by_buffer_temporarily_with_options_inner(
+        &self,
+        buffer: &[u8],
+        file_name: String,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        Self::check_temporary_expiration(upload_options.expiration)?;
+
+        let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
 
-        let cc = cc as i64;
+        let encoder = self.get_data_encoder(&upload_options, &mime_type);
 
-        let file_name = file_name.into();
+        let threshold = self.get_inline_threshold(&file_name, &upload_options)?;
+
+        let file_size = buffer.len();
+
+        self.check_quota(upload_options.owner.as_deref(), file_size as u64).await?;
 
         let file_id = ObjectId::new();
 
         let mut file_item_raw = doc! {
             "_id": file_id,
+            "file_size": file_size as i64,
             "file_name": file_name,
             "count": 1i32
         };
 
-        let is_stream = cc == buffer_size as i64;
+        let is_stream = file_size as u64 > threshold;
 
-        let (hash_1, hash_2, hash_3, hash_4) = if is_stream {
-            let (chunk_id, file_size, hash) =
-                match self.upload_from_stream_and_hash(file_id, file_data, reader).await {
-                    Ok(id) => id,
-                    Err(err) => {
-                        if self.delete_file_chunks(file_id).await.is_err() {
-                            // do nothing
-                        }
+        if is_stream {
+            let chunk_id = match self
+                .upload_from_stream(
+                    session,
+                    &mut ChunkReader::new(
+                        buffer,
+                        file_id,
+                        self.chunk_size as usize,
+                        &encoder,
+                        None,
+                    ),
+                )
+                .await
+            {
+                Ok(id) => id,
+                Err(err) => {
+                    if self.delete_file_chunks(file_id, session).await.is_err() {
+                        // do nothing
+                    }
 
-                        return Err(err);
-                    },
-                };
+                    return Err(err);
+                },
+            };
 
-            file_item_raw.insert("file_size", file_size);
             file_item_raw.insert("chunk_id", chunk_id);
+            file_item_raw.insert("chunk_size", self.chunk_size as i32);
 
-            hash
+            encoder.insert_chunk_fields(&mut file_item_raw);
         } else {
-            unsafe {
-                file_data.set_len(cc as usize);
-            }
+            encoder.insert_file_data(&mut file_item_raw, file_id, buffer.to_vec())?;
+        }
 
-            let hash = get_hash_by_buffer(&file_data);
+        file_item_raw.insert("mime_type", mime_type.as_ref());
 
-            file_item_raw.insert("file_size", cc);
-            file_item_raw.insert(
-                "file_data",
-                Bson::Binary(Binary {
-                    subtype: BinarySubtype::Generic, bytes: file_data
-                }),
-            );
+        if let Some(metadata) = upload_options.metadata {
+            file_item_raw.insert("metadata", metadata);
+        }
 
-            hash
-        };
+        insert_owner(&mut file_item_raw, upload_options.owner);
 
-        let mut options = FindOneAndUpdateOptions::default();
-        options.return_document = Some(ReturnDocument::After);
-        options.projection = Some(file_exist_projection());
+        let now = DateTime::now();
 
-        let result = self
-            .collections
-            .files
-            .find_one_and_update(
-                doc! {
-                   "hash_1": hash_1,
-                   "hash_2": hash_2,
-                   "hash_3": hash_3,
+        let expire = self.get_temporary_expiration(now, upload_options.expiration);
+        let expire_chunks = DateTime::from_millis(
+            expire.timestamp_millis().saturating_add(TEMPORARY_CHUNK_EXTRA_LIFE_TIME),
+        );
+
+        file_item_raw.insert("create_time", now);
+
+        file_item_raw.insert("expire_at", expire);
+
+        insert_read_limit(&mut file_item_raw, upload_options.read_limit);
+
+        if is_stream {
+            session
+                .update_many(
+                    &self.collections.files_chunks,
+                    doc! {
+                        "file_id": file_id
+                    },
+                    doc! {
+                        "$set": {
+                            "expire_at": expire_chunks
+                        }
+                    },
+                )
+                .await?;
+        }
+
+        session.insert_one(&self.collections.files, file_item_raw).await?;
+
+        Ok(file_id)
+    }
+}
+
+impl FileCenter {
+    /// Check the quota of the owner before the data is read, and stop reading one byte after the remaining budget, so that an upload which exceeds the quota does not store more data than that before it is rejected.
+    async fn limit_reader_by_quota<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        upload_options: &UploadOptions,
+    ) -> Result<Take<R>, FileCenterError> {
+        let limit = match self.get_quota_budget(upload_options.owner.as_deref()).await? {
+            Some(budget) => budget.saturating_add(1),
+            None => u64::MAX,
+        };
+
+        Ok(reader.take(limit))
+    }
+
+    async fn upload_from_stream_and_hash(
+        &self,
+        session: &mut Session,
+        file_id: ObjectId,
+        encoder: &DataEncoder,
+        first_chunk_plus_one: Vec<u8>,
+        source: impl AsyncRead + Unpin,
+    ) -> Result<(ObjectId, i64, (i64, i64, i64, i64)), FileCenterError> {
+        let mut hasher = Hasher::new();
+
+        let mut reader = ChunkReader::new(
+            Cursor::new(first_chunk_plus_one).chain(source),
+            file_id,
+            self.chunk_size as usize,
+            encoder,
+            Some(&mut hasher),
+        );
+
+        let inserted_id = self.upload_from_stream(session, &mut reader).await?;
+
+        let file_size = reader.get_file_size();
+
+        let hash = separate_hash(&hasher.finalize());
+
+        Ok((inserted_id, file_size, hash))
+    }
+
+    async fn upload_from_stream_and_no_hash(
+        &self,
+        session: &mut Session,
+        file_id: ObjectId,
+        encoder: &DataEncoder,
+        first_chunk_plus_one: Vec<u8>,
+        source: impl AsyncRead + Unpin,
+    ) -> Result<(ObjectId, i64), FileCenterError> {
+        let mut reader = ChunkReader::new(
+            Cursor::new(first_chunk_plus_one).chain(source),
+            file_id,
+            self.chunk_size as usize,
+            encoder,
+            None,
+        );
+
+        let inserted_id = self.upload_from_stream(session, &mut reader).await?;
+
+        Ok((inserted_id, reader.get_file_size()))
+    }
+
+    /// Input a file to the file center via a reader.
+    #[inline]
+    pub async fn put_file_by_reader<R: AsyncRead + Unpin, S: Into<String>>(
+        &self,
+        reader: R,
+        file_name: S,
+        mime_type: Option<Mime>,
+    ) -> Result<ObjectId, FileCenterError> {
+        self.put_file_by_reader_with_options(reader, file_name, mime_type, UploadOptions::default())
+            .await
+    }
+
+    /// Input a file to the file center via a reader with extra options.
+    pub async fn put_file_by_reader_with_options<R: AsyncRead + Unpin, S: Into<String>>(
+        &self,
+        reader: R,
+        file_name: S,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+    ) -> Result<ObjectId, FileCenterError> {
+        let mut session = self.start_session().await?;
+
+        let result = self
+            .put_file_by_reader_with_options_inner(
+                reader,
+                file_name,
+                mime_type,
+                upload_options,
+                &mut session,
+            )
+            .await;
+
+        session.end(result).await
+    }
+
+    async fn put_file_by_reader_with_options_inner<R: AsyncRead + Unpin, S: Into<String>>(
+        &self,
+        reader: R,
+        file_name: S,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        let file_name: String = file_name.into();
+
+        let threshold = self.get_inline_threshold(&file_name, &upload_options)?;
+
+        let mut reader = self.limit_reader_by_quota(reader, &upload_options).await?;
+
+        let buffer_size = threshold + 1;
+
+        let mut file_data = Vec::new();
+
+        // read one more byte than the threshold to know whether the data should be separated into chunks, without allocating the whole buffer in advance
+        (&mut reader).take(buffer_size).read_to_end(&mut file_data).await?;
+
+        let cc = file_data.len() as i64;
+
+        let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
+
+        let encoder = self.get_data_encoder(&upload_options, &mime_type);
+
+        let file_id = ObjectId::new();
+
+        let mut file_item_raw = doc! {
+            "_id": file_id,
+            "count": 1i32
+        };
+
+        let is_stream = cc == buffer_size as i64;
+
+        let (hash_1, hash_2, hash_3, hash_4) = if is_stream {
+            let (chunk_id, file_size, hash) = match self
+                .upload_from_stream_and_hash(session, file_id, &encoder, file_data, reader)
+                .await
+            {
+                Ok(id) => id,
+                Err(err) => {
+                    if self.delete_file_chunks(file_id, session).await.is_err() {
+                        // do nothing
+                    }
+
+                    return Err(err);
+                },
+            };
+
+            file_item_raw.insert("file_size", file_size);
+            file_item_raw.insert("chunk_id", chunk_id);
+            file_item_raw.insert("chunk_size", self.chunk_size as i32);
+
+            encoder.insert_chunk_fields(&mut file_item_raw);
+
+            hash
+        } else {
+            let hash = get_hash_by_buffer(&file_data);
+
+            file_item_raw.insert("file_size", cc);
+            encoder.insert_file_data(&mut file_item_raw, file_id, file_data)?;
+
+            hash
+        };
+
+        self.insert_file_item_with_hash(
+            file_item_raw,
+            (hash_1, hash_2, hash_3, hash_4),
+            file_name,
+            mime_type,
+            upload_options,
+            session,
+        )
+        .await
+    }
+
+    /// Temporarily input a file to the file center via a reader.
+    #[inline]
+    pub async fn put_file_by_reader_temporarily<R: AsyncRead + Unpin, S: Into<String>>(
+        &self,
+        reader: R,
+        file_name: S,
+        mime_type: Option<Mime>,
+    ) -> Result<ObjectId, FileCenterError> {
+        self.put_file_by_reader_temporarily_with_options(
+            reader,
+            file_name,
+            mime_type,
+            UploadOptions::default(),
+        )
+        .await
+    }
+
+    /// Temporarily input a file to the file center via a reader with extra options.
+    pub async fn put_file_by_reader_temporarily_with_options<
+        R: AsyncRead + Unpin,
+        S: Into<String>,
+    >(
+        &self,
+        reader: R,
+        file_name: S,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+    ) -> Result<ObjectId, FileCenterError> {
+        let mut session = self.start_session().await?;
+
+        let result = self
+            .put_file_by_reader_temporarily_with_options_inner(
+                reader,
+                file_name,
+                mime_type,
+                upload_options,
+                &mut session,
+            )
+            .await;
+
+        session.end(result).await
+    }
+
+    async fn put_file_by_reader_temporarily_with_options_inner<
+        R: AsyncRead + Unpin,
+        S: Into<String>,
+    >(
+        &self,
+        reader: R,
+        file_name: S,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        Self::check_temporary_expiration(upload_options.expiration)?;
+
+        let file_name: String = file_name.into();
+
+        let threshold = self.get_inline_threshold(&file_name, &upload_options)?;
+
+        let mut reader = self.limit_reader_by_quota(reader, &upload_options).await?;
+
+        let buffer_size = threshold + 1;
+
+        let mut file_data = Vec::new();
+
+        // read one more byte than the threshold to know whether the data should be separated into chunks, without allocating the whole buffer in advance
+        (&mut reader).take(buffer_size).read_to_end(&mut file_data).await?;
+
+        let cc = file_data.len() as i64;
+
+        let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
+
+        let encoder = self.get_data_encoder(&upload_options, &mime_type);
+
+        let file_id = ObjectId::new();
+
+        let mut file_item_raw = doc! {
+            "_id": file_id,
+            "count": 1i32
+        };
+
+        let is_stream = cc == buffer_size as i64;
+
+        if is_stream {
+            let (chunk_id, file_size) = match self
+                .upload_from_stream_and_no_hash(session, file_id, &encoder, file_data, reader)
+                .await
+            {
+                Ok(id) => id,
+                Err(err) => {
+                    if self.delete_file_chunks(file_id, session).await.is_err() {
+                        // do nothing
+                    }
+
+                    return Err(err);
+                },
+            };
+
+            file_item_raw.insert("file_size", file_size);
+            file_item_raw.insert("chunk_id", chunk_id);
+            file_item_raw.insert("chunk_size", self.chunk_size as i32);
+
+            encoder.insert_chunk_fields(&mut file_item_raw);
+        } else {
+            file_item_raw.insert("file_size", cc);
+            encoder.insert_file_data(&mut file_item_raw, file_id, file_data)?;
+        };
+
+        self.insert_temporary_file_item(
+            file_item_raw,
+            file_name,
+            mime_type,
+            upload_options,
+            session,
+        )
+        .await
+    }
+}
+
+impl FileCenter {
+    /// Open a writer which uploads a file to the file center. The file is stored after `FileUploadStream::finish` is called. If the file has an owner, the quota of the owner is checked first, and writing more data than the owner can still put fails.
+    #[inline]
+    pub async fn open_upload_stream<S: Into<String>>(
+        &self,
+        file_name: S,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+    ) -> Result<FileUploadStream<'_>, FileCenterError> {
+        let budget = self.get_quota_budget(upload_options.owner.as_deref()).await?;
+
+        let file_name: String = file_name.into();
+
+        let threshold = self.get_inline_threshold(&file_name, &upload_options)?;
+
+        Ok(FileUploadStream::new(
+            self,
+            file_name,
+            mime_type,
+            upload_options,
+            false,
+            threshold,
+            budget,
+        ))
+    }
+
+    /// Open a writer which temporarily uploads a file to the file center. The file is stored after `FileUploadStream::finish` is called. If the file has an owner, the quota of the owner is checked first, and writing more data than the owner can still put fails.
+    #[inline]
+    pub async fn open_upload_stream_temporarily<S: Into<String>>(
+        &self,
+        file_name: S,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+    ) -> Result<FileUploadStream<'_>, FileCenterError> {
+        Self::check_temporary_expiration(upload_options.expiration)?;
+
+        let budget = self.get_quota_budget(upload_options.owner.as_deref()).await?;
+
+        let file_name: String = file_name.into();
+
+        let threshold = self.get_inline_threshold(&file_name, &upload_options)?;
+
+        Ok(FileUploadStream::new(
+            self,
+            file_name,
+            mime_type,
+            upload_options,
+            true,
+            threshold,
+            budget,
+        ))
+    }
+}
+
+impl FileCenter {
+    /// Insert a file item whose data has been stored and whose hash has been computed. If a file which has the same content already exists, the stored data is removed and a reference to the existing file is created instead.
+    pub(crate) async fn insert_file_item_with_hash(
+        &self,
+        mut file_item_raw: Document,
+        (hash_1, hash_2, hash_3, hash_4): (i64, i64, i64, i64),
+        file_name: String,
+        mime_type: Mime,
+        upload_options: UploadOptions,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        let file_id = file_item_raw.get_object_id("_id")?;
+        let is_stream = file_item_raw.get("chunk_id").is_some();
+
+        let file_size = file_item_raw.get_i64("file_size")? as u64;
+
+        if let Err(err) = self.check_quota(upload_options.owner.as_deref(), file_size).await {
+            if is_stream && self.delete_file_chunks(file_id, session).await.is_err() {
+                // do nothing
+            }
+
+            return Err(err);
+        }
+
+        let encryption_key_id = file_item_raw.get_str("encryption_key_id").ok().map(String::from);
+
+        let mut options = FindOneAndUpdateOptions::default();
+        options.return_document = Some(ReturnDocument::After);
+        options.projection = Some(file_reference_projection());
+
+        let result = session
+            .find_one_and_update(
+                &self.collections.files,
+                doc! {
+                   "hash_1": hash_1,
+                   "hash_2": hash_2,
+                   "hash_3": hash_3,
                    "hash_4": hash_4,
+                   "encryption_key_id": encryption_key_id,
                 },
                 doc! {
                     "$inc": {
                         "count": 1
                     }
                 },
-                Some(options),
+                options,
             )
             .await?;
 
         match result {
             Some(result) => {
-                if is_stream && self.delete_file_chunks(file_id).await.is_err() {
+                if is_stream && self.delete_file_chunks(file_id, session).await.is_err() {
                     // do nothing
                 }
 
-                Ok(result.get_object_id("_id")?)
+                self.insert_file_reference(
+                    result,
+                    file_name,
+                    mime_type,
+                    upload_options.metadata,
+                    upload_options.owner,
+                    session,
+                )
+                .await
             },
             None => {
                 file_item_raw.insert("hash_1", hash_1);
@@ -1521,223 +3371,831 @@ impl FileCenter {
                 file_item_raw.insert("hash_3", hash_3);
                 file_item_raw.insert("hash_4", hash_4);
 
-                let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
+                file_item_raw.insert("file_name", file_name);
+                file_item_raw.insert("mime_type", mime_type.as_ref());
+
+                if let Some(metadata) = upload_options.metadata {
+                    file_item_raw.insert("metadata", metadata);
+                }
+
+                insert_owner(&mut file_item_raw, upload_options.owner.clone());
+
+                file_item_raw.insert("create_time", DateTime::now());
+
+                session.insert_one(&self.collections.files, file_item_raw).await?;
+
+                self.add_usage(upload_options.owner.as_deref(), file_size as i64, 1, session)
+                    .await?;
+
+                Ok(file_id)
+            },
+        }
+    }
+
+    /// Insert a temporary file item whose data has been stored.
+    pub(crate) async fn insert_temporary_file_item(
+        &self,
+        mut file_item_raw: Document,
+        file_name: String,
+        mime_type: Mime,
+        upload_options: UploadOptions,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        let file_id = file_item_raw.get_object_id("_id")?;
+        let is_stream = file_item_raw.get("chunk_id").is_some();
+
+        let file_size = file_item_raw.get_i64("file_size")? as u64;
+
+        let checked = match Self::check_temporary_expiration(upload_options.expiration) {
+            Ok(()) => self.check_quota(upload_options.owner.as_deref(), file_size).await,
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = checked {
+            if is_stream && self.delete_file_chunks(file_id, session).await.is_err() {
+                // do nothing
+            }
+
+            return Err(err);
+        }
+
+        file_item_raw.insert("file_name", file_name);
+        file_item_raw.insert("mime_type", mime_type.as_ref());
+
+        if let Some(metadata) = upload_options.metadata {
+            file_item_raw.insert("metadata", metadata);
+        }
+
+        insert_owner(&mut file_item_raw, upload_options.owner);
+
+        let now = DateTime::now();
+
+        let expire = self.get_temporary_expiration(now, upload_options.expiration);
+        let expire_chunks = DateTime::from_millis(
+            expire.timestamp_millis().saturating_add(TEMPORARY_CHUNK_EXTRA_LIFE_TIME),
+        );
+
+        file_item_raw.insert("create_time", now);
+
+        file_item_raw.insert("expire_at", expire);
+
+        insert_read_limit(&mut file_item_raw, upload_options.read_limit);
+
+        if is_stream {
+            session
+                .update_many(
+                    &self.collections.files_chunks,
+                    doc! {
+                        "file_id": file_id
+                    },
+                    doc! {
+                        "$set": {
+                            "expire_at": expire_chunks
+                        }
+                    },
+                )
+                .await?;
+        }
+
+        session.insert_one(&self.collections.files, file_item_raw).await?;
+
+        Ok(file_id)
+    }
+}
+
+impl FileCenter {
+    async fn get_hash_by_chunks(
+        &self,
+        file_id: ObjectId,
+        decryption_key: Option<EncryptionKey>,
+        content_encoding: Option<ContentEncoding>,
+    ) -> Result<(i64, i64, i64, i64), FileCenterError> {
+        let mut stream = self.open_download_stream(file_id, None).await?;
+
+        if let Some(decryption_key) = decryption_key {
+            stream = decryption_key.decrypt_stream(stream, file_id, 0);
+        }
+
+        if let Some(content_encoding) = content_encoding {
+            stream = content_encoding.decode_stream(stream);
+        }
+
+        let mut hasher = Hasher::new();
+
+        while let Some(chunk) = stream.try_next().await? {
+            hasher.update(chunk.get_ref());
+        }
+
+        Ok(separate_hash(&hasher.finalize()))
+    }
+
+    /// Turn a temporary file into a perennial file. The ID of the file is not changed. If there is already a perennial file which has the same content, the temporary file becomes a reference to it and its own data is removed. Returns `false` if the temporary file does not exist or has expired.
+    pub async fn persist_temporary_file(&self, id: ObjectId) -> Result<bool, FileCenterError> {
+        let collection_files = &self.collections.files;
+
+        let mut options = FindOneOptions::default();
+        options.projection = Some(doc! {
+            "_id": 0,
+            "file_size": 1,
+            "file_data": 1,
+            "chunk_id": 1,
+            "content_encoding": 1,
+            "encryption_key_id": 1,
+            "owner": 1,
+        });
+
+        let file_item = collection_files
+            .find_one(
+                Some(doc! {
+                    "_id": id,
+                    "expire_at": {
+                        "$gt": DateTime::now()
+                    }
+                }),
+                Some(options),
+            )
+            .await?;
+
+        let file_item = match file_item {
+            Some(file_item) => file_item,
+            None => return Ok(false),
+        };
+
+        let is_stream = file_item.get("chunk_id").is_some();
+
+        let decryption_key = self.get_decryption_key(&file_item)?;
+        let content_encoding = get_content_encoding(&file_item)?;
+
+        let (hash_1, hash_2, hash_3, hash_4) = if is_stream {
+            self.get_hash_by_chunks(id, decryption_key, content_encoding).await?
+        } else {
+            let file_data = file_item.get_binary_generic("file_data")?;
+
+            let decrypted_data;
+
+            let file_data = match decryption_key {
+                Some(decryption_key) => {
+                    decrypted_data =
+                        decryption_key.decrypt(file_data, &file_data_associated_data(id))?;
+
+                    &decrypted_data
+                },
+                None => file_data,
+            };
+
+            match content_encoding {
+                Some(content_encoding) => get_hash_by_buffer(&content_encoding.decode(file_data)?),
+                None => get_hash_by_buffer(file_data),
+            }
+        };
+
+        loop {
+            let mut session = self.start_session().await?;
+
+            let result = loop {
+                let result = self
+                    .persist_temporary_file_inner(
+                        id,
+                        &file_item,
+                        (hash_1, hash_2, hash_3, hash_4),
+                        &mut session,
+                    )
+                    .await;
+
+                if let Some(result) = session.end_or_restart(result).await {
+                    break result;
+                }
+            };
+
+            match result {
+                // another file with the same content has just been put, merge into it
+                Err(FileCenterError::MongoDBError(err)) if is_duplicate_key_error(&err) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    async fn persist_temporary_file_inner(
+        &self,
+        id: ObjectId,
+        file_item: &Document,
+        (hash_1, hash_2, hash_3, hash_4): (i64, i64, i64, i64),
+        session: &mut Session,
+    ) -> Result<bool, FileCenterError> {
+        let collection_files = &self.collections.files;
+
+        let is_stream = file_item.get("chunk_id").is_some();
+        let owner = file_item.get_str("owner").ok();
+
+        let mut options = FindOneAndUpdateOptions::default();
+        options.return_document = Some(ReturnDocument::After);
+        options.projection = Some(file_reference_projection());
+
+        let result = session
+            .find_one_and_update(
+                collection_files,
+                doc! {
+                   "hash_1": hash_1,
+                   "hash_2": hash_2,
+                   "hash_3": hash_3,
+                   "hash_4": hash_4,
+                   "encryption_key_id": file_item.get_str("encryption_key_id").ok(),
+                },
+                doc! {
+                    "$inc": {
+                        "count": 1
+                    }
+                },
+                options,
+            )
+            .await?;
+
+        if let Some(content) = result {
+            let content_id = content.get_object_id("_id")?;
+            let file_size = content.get_i64("file_size")?;
+
+            let result = session
+                .update_one(
+                    collection_files,
+                    doc! {
+                        "_id": id,
+                        "expire_at": {
+                            "$gt": DateTime::now()
+                        }
+                    },
+                    doc! {
+                        "$set": {
+                            "content_id": content_id,
+                            "file_size": file_size,
+                        },
+                        "$unset": {
+                            "file_data": "",
+                            "chunk_id": "",
+                            "chunk_size": "",
+                            "content_encoding": "",
+                            "encryption_key_id": "",
+                            "count": "",
+                            "expire_at": "",
+                            "remaining_reads": "",
+                            "unlimited_reads": "",
+                        }
+                    },
+                )
+                .await;
+
+            let matched = match result {
+                Ok(result) => result.matched_count > 0,
+                Err(err) => {
+                    if self.decrease_file_count(content_id, false, session).await.is_err() {
+                        // do nothing
+                    }
+
+                    return Err(err.into());
+                },
+            };
+
+            if !matched {
+                self.decrease_file_count(content_id, false, session).await?;
+
+                return Ok(false);
+            }
+
+            if is_stream && self.delete_file_chunks(id, session).await.is_err() {
+                // do nothing
+            }
+
+            self.add_usage(owner, file_size, 1, session).await?;
+
+            return Ok(true);
+        }
+
+        // the file item is updated first, so that the chunks keep their expiration if the file item has expired or been removed in the meantime
+        let result = session
+            .update_one(
+                collection_files,
+                doc! {
+                    "_id": id,
+                    "expire_at": {
+                        "$gt": DateTime::now()
+                    }
+                },
+                doc! {
+                    "$set": {
+                        "hash_1": hash_1,
+                        "hash_2": hash_2,
+                        "hash_3": hash_3,
+                        "hash_4": hash_4,
+                        "count": 1i32,
+                    },
+                    "$unset": {
+                        "expire_at": "",
+                        "remaining_reads": "",
+                        "unlimited_reads": "",
+                    }
+                },
+            )
+            .await?;
+
+        if result.matched_count == 0 {
+            return Ok(false);
+        }
+
+        if is_stream {
+            session
+                .update_many(
+                    &self.collections.files_chunks,
+                    doc! {
+                        "file_id": id
+                    },
+                    doc! {
+                        "$unset": {
+                            "expire_at": ""
+                        }
+                    },
+                )
+                .await?;
+        }
+
+        self.add_usage(owner, file_item.get_i64("file_size")?, 1, session).await?;
+
+        Ok(true)
+    }
+}
+
+impl FileCenter {
+    /// Remove all unused file meta and file chunks in this file center.
+    #[inline]
+    pub async fn clear_garbage(&self) -> Result<GarbageReport, FileCenterError> {
+        self.clear_garbage_with_options(GarbageOptions::default()).await
+    }
+
+    /// Remove all unused file meta and file chunks in this file center, or only find them if `dry_run` is set. The file center is scanned in bounded batches, but unlike `clear_garbage_incrementally`, no checkpoint is used.
+    ///
+    /// The chunks of a file are never removed while one of them was inserted within the grace period, so that the uploads in progress are not broken. The chunks of temporary files are left to the TTL index.
+    #[inline]
+    pub async fn clear_garbage_with_options(
+        &self,
+        options: GarbageOptions,
+    ) -> Result<GarbageReport, FileCenterError> {
+        let options = IncrementalGarbageOptions {
+            grace_period: options.grace_period,
+            dry_run: options.dry_run,
+            ..IncrementalGarbageOptions::default()
+        };
+
+        self.collect_garbage(options, false, &mut (GARBAGE_PHASE_ITEMS, None)).await
+    }
+
+    /// Remove the garbage in bounded batches, which is suitable for a big file center. The position which has been scanned is stored in the settings collection after every batch, so the next call resumes from it if this one stops because of `max_batches` or an error. The position is reset after the whole file center has been scanned.
+    ///
+    /// The chunks of a file are never removed while one of them was inserted within the grace period, so that the uploads in progress are not broken. The chunks of temporary files are left to the TTL index.
+    #[inline]
+    pub async fn clear_garbage_incrementally(
+        &self,
+        options: IncrementalGarbageOptions,
+    ) -> Result<GarbageReport, FileCenterError> {
+        let mut position = self.load_garbage_checkpoint().await?;
+
+        self.collect_garbage(options, true, &mut position).await
+    }
+
+    /// Scan the file center in batches from `position`, which is updated after every batch. If `checkpoint` is `true`, the position is stored after every batch as well, unless it is a dry run.
+    pub(crate) async fn collect_garbage(
+        &self,
+        options: IncrementalGarbageOptions,
+        checkpoint: bool,
+        position: &mut (&'static str, Option<ObjectId>),
+    ) -> Result<GarbageReport, FileCenterError> {
+        let mut report = GarbageReport {
+            dry_run: options.dry_run,
+            ..GarbageReport::default()
+        };
+
+        let checkpoint = checkpoint && !options.dry_run;
+
+        let batch_size = options.batch_size.max(1) as i64;
+
+        let (mut phase, mut last_id) = *position;
+
+        let mut batches = 0;
+
+        loop {
+            if let Some(max_batches) = options.max_batches {
+                if batches >= max_batches {
+                    return Ok(report);
+                }
+            }
+
+            let mut find_options = FindOptions::default();
+            find_options.limit = Some(batch_size);
+
+            if phase == GARBAGE_PHASE_ITEMS {
+                find_options.sort = Some(doc! {
+                    "_id": 1
+                });
+                find_options.projection = Some(doc! {
+                    "_id": 1,
+                    "chunk_id": 1,
+                    "content_id": 1,
+                    "count": 1,
+                });
+
+                let filter = match last_id {
+                    Some(last_id) => doc! {
+                        "_id": {
+                            "$gt": last_id
+                        }
+                    },
+                    None => doc! {},
+                };
+
+                let items = self
+                    .collections
+                    .files
+                    .find(filter, find_options)
+                    .await?
+                    .collect::<Result<Vec<Document>, _>>()
+                    .await?;
+
+                match items.last() {
+                    Some(item) => {
+                        last_id = Some(item.get_object_id("_id")?);
+                    },
+                    None => {
+                        phase = GARBAGE_PHASE_CHUNKS;
+                        last_id = None;
+
+                        continue;
+                    },
+                }
+
+                self.clear_garbage_items_batch(items, options.dry_run, &mut report).await?;
+            } else {
+                find_options.sort = Some(doc! {
+                    "file_id": 1
+                });
+                find_options.projection = Some(doc! {
+                    "_id": 0,
+                    "file_id": 1,
+                });
+
+                // the chunks of temporary files are removed by the TTL index
+                let mut filter = doc! {
+                    "expire_at": {
+                        "$exists": false
+                    }
+                };
+
+                if let Some(last_id) = last_id {
+                    filter.insert("file_id", doc! {
+                        "$gt": last_id
+                    });
+                }
+
+                let result = self.collections.files_chunks.find(filter, find_options).await?;
+
+                let mut file_ids = collect_ids(result, "file_id").await?;
+
+                // the chunks are sorted by file IDs
+                file_ids.dedup();
+
+                match file_ids.last() {
+                    Some(file_id) => {
+                        last_id = Some(*file_id);
+                    },
+                    None => {
+                        if checkpoint {
+                            self.collections
+                                .settings
+                                .delete_one(
+                                    doc! {
+                                        "_id": SETTING_GARBAGE_CHECKPOINT
+                                    },
+                                    None,
+                                )
+                                .await?;
+                        }
+
+                        *position = (GARBAGE_PHASE_ITEMS, None);
+
+                        report.completed = true;
+
+                        return Ok(report);
+                    },
+                }
+
+                let grace_period = options.grace_period.as_millis().min(i64::MAX as u128) as i64;
+                let threshold = DateTime::now().timestamp_millis().saturating_sub(grace_period);
+
+                // the chunks stored before `create_time` was added to them only have the time when the upload started
+                file_ids.retain(|file_id| file_id.timestamp().timestamp_millis() <= threshold);
+
+                // an upload which is still in progress keeps inserting chunks
+                if !file_ids.is_empty() {
+                    let active_ids = self
+                        .collections
+                        .files_chunks
+                        .distinct(
+                            "file_id",
+                            doc! {
+                                "file_id": {
+                                    "$in": file_ids.clone()
+                                },
+                                "create_time": {
+                                    "$gt": DateTime::from_millis(threshold)
+                                }
+                            },
+                            None,
+                        )
+                        .await?;
+
+                    file_ids.retain(|file_id| !active_ids.contains(&Bson::ObjectId(*file_id)));
+                }
+
+                self.clear_garbage_chunks_batch(file_ids, options.dry_run, &mut report).await?;
+            }
+
+            batches += 1;
+
+            *position = (phase, last_id);
+
+            if checkpoint {
+                self.save_garbage_checkpoint(phase, last_id).await?;
+            }
+        }
+    }
+
+    async fn clear_garbage_items_batch(
+        &self,
+        items: Vec<Document>,
+        dry_run: bool,
+        report: &mut GarbageReport,
+    ) -> Result<(), FileCenterError> {
+        let mut zero_count_ids = Vec::new();
+        let mut chunk_ids = Vec::new();
+        let mut content_ids = Vec::new();
+
+        for item in items {
+            let id = item.get_object_id("_id")?;
+
+            if let Some(count) = item.get("count").and_then(|count| count.as_i32()) {
+                if count <= 0 {
+                    zero_count_ids.push(id);
+
+                    continue;
+                }
+            }
+
+            if let Ok(chunk_id) = item.get_object_id("chunk_id") {
+                chunk_ids.push((id, chunk_id));
+            } else if let Ok(content_id) = item.get_object_id("content_id") {
+                content_ids.push((id, content_id));
+            }
+        }
+
+        let mut orphan_ids = Vec::new();
+
+        if !chunk_ids.is_empty() {
+            let result = self
+                .collections
+                .files_chunks
+                .find(
+                    doc! {
+                        "_id": {
+                            "$in": chunk_ids.iter().map(|(_, chunk_id)| *chunk_id).collect::<Vec<_>>()
+                        }
+                    },
+                    None,
+                )
+                .await?;
+
+            let existing_ids = collect_ids(result, "_id").await?;
 
-                file_item_raw.insert("mime_type", mime_type.as_ref());
+            orphan_ids.extend(
+                chunk_ids
+                    .into_iter()
+                    .filter(|(_, chunk_id)| !existing_ids.contains(chunk_id))
+                    .map(|(id, _)| id),
+            );
+        }
 
-                file_item_raw.insert("create_time", DateTime::now());
+        if !content_ids.is_empty() {
+            let result = self
+                .collections
+                .files
+                .find(
+                    doc! {
+                        "_id": {
+                            "$in": content_ids.iter().map(|(_, content_id)| *content_id).collect::<Vec<_>>()
+                        }
+                    },
+                    None,
+                )
+                .await?;
 
-                self.collections.files.insert_one(file_item_raw, None).await?;
+            let existing_ids = collect_ids(result, "_id").await?;
 
-                Ok(file_id)
-            },
+            orphan_ids.extend(
+                content_ids
+                    .into_iter()
+                    .filter(|(_, content_id)| !existing_ids.contains(content_id))
+                    .map(|(id, _)| id),
+            );
         }
-    }
-
-    /// Temporarily input a file to the file center via a reader.
-    pub async fn put_file_by_reader_temporarily<R: AsyncRead + Unpin, S: Into<String>>(
-        &self,
-        mut reader: R,
-        file_name: S,
-        mime_type: Option<Mime>,
-    ) -> Result<ObjectId, FileCenterError> {
-        let buffer_size = self.file_size_threshold as usize + 1;
 
-        let mut file_data = vec![0u8; buffer_size];
+        // unlike the file items whose counts have dropped to 0, the orphans are still counted in the usage counters of their owners
+        if !dry_run {
+            self.remove_garbage_items_usage(&orphan_ids).await?;
+        }
 
-        let mut cc = 0;
+        self.remove_garbage_items(&orphan_ids, false, dry_run, report).await?;
+        self.remove_garbage_items(&zero_count_ids, true, dry_run, report).await?;
 
-        // read to full
-        loop {
-            let c = match reader.read(&mut file_data[cc..]).await {
-                Ok(0) => break,
-                Ok(c) => c,
-                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
-                Err(e) => return Err(e.into()),
-            };
+        report.add_orphan_items(orphan_ids);
+        report.add_zero_count_items(zero_count_ids);
 
-            cc += c;
+        Ok(())
+    }
 
-            if cc == buffer_size {
-                break;
-            }
+    async fn clear_garbage_chunks_batch(
+        &self,
+        file_ids: Vec<ObjectId>,
+        dry_run: bool,
+        report: &mut GarbageReport,
+    ) -> Result<(), FileCenterError> {
+        if file_ids.is_empty() {
+            return Ok(());
         }
 
-        let cc = cc as i64;
+        let result = self
+            .collections
+            .files
+            .find(
+                doc! {
+                    "_id": {
+                        "$in": file_ids.clone()
+                    }
+                },
+                None,
+            )
+            .await?;
 
-        let file_name = file_name.into();
+        let existing_ids = collect_ids(result, "_id").await?;
 
-        let file_id = ObjectId::new();
+        let orphan_ids: Vec<ObjectId> =
+            file_ids.into_iter().filter(|file_id| !existing_ids.contains(file_id)).collect();
 
-        let mut file_item_raw = doc! {
-            "_id": file_id,
-            "file_name": file_name,
-            "count": 1i32
-        };
+        self.remove_garbage_chunks(&orphan_ids, dry_run, report).await?;
 
-        let is_stream = cc == buffer_size as i64;
+        report.add_orphan_chunk_file_ids(orphan_ids);
 
-        if is_stream {
-            let (chunk_id, file_size) =
-                match self.upload_from_stream_and_no_hash(file_id, file_data, reader).await {
-                    Ok(id) => id,
-                    Err(err) => {
-                        if self.delete_file_chunks(file_id).await.is_err() {
-                            // do nothing
-                        }
+        Ok(())
+    }
+
+    pub(crate) async fn load_garbage_checkpoint(
+        &self,
+    ) -> Result<(&'static str, Option<ObjectId>), FileCenterError> {
+        let checkpoint = self
+            .collections
+            .settings
+            .find_one(
+                doc! {
+                    "_id": SETTING_GARBAGE_CHECKPOINT
+                },
+                None,
+            )
+            .await?;
+
+        match checkpoint {
+            Some(checkpoint) => {
+                let value = checkpoint.get_document("value")?;
 
-                        return Err(err);
+                let phase = match value.get_str("phase")? {
+                    GARBAGE_PHASE_ITEMS => GARBAGE_PHASE_ITEMS,
+                    GARBAGE_PHASE_CHUNKS => GARBAGE_PHASE_CHUNKS,
+                    _ => {
+                        return Err(FileCenterError::DocumentError(
+                            ValueAccessError::UnexpectedType,
+                        ));
                     },
                 };
 
-            file_item_raw.insert("file_size", file_size);
-            file_item_raw.insert("chunk_id", chunk_id);
-        } else {
-            unsafe {
-                file_data.set_len(cc as usize);
-            }
-
-            file_item_raw.insert("file_size", cc);
-            file_item_raw.insert(
-                "file_data",
-                Bson::Binary(Binary {
-                    subtype: BinarySubtype::Generic, bytes: file_data
-                }),
-            );
-        };
+                let last_id = match value.get("last_id") {
+                    Some(Bson::ObjectId(last_id)) => Some(*last_id),
+                    Some(Bson::Null) | None => None,
+                    Some(_) => {
+                        return Err(FileCenterError::DocumentError(
+                            ValueAccessError::UnexpectedType,
+                        ));
+                    },
+                };
 
-        let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
+                Ok((phase, last_id))
+            },
+            None => Ok((GARBAGE_PHASE_ITEMS, None)),
+        }
+    }
 
-        file_item_raw.insert("mime_type", mime_type.as_ref());
+    async fn save_garbage_checkpoint(
+        &self,
+        phase: &str,
+        last_id: Option<ObjectId>,
+    ) -> Result<(), FileCenterError> {
+        let mut options = UpdateOptions::default();
+        options.upsert = Some(true);
 
-        let now = DateTime::now();
+        self.collections
+            .settings
+            .update_one(
+                doc! {
+                    "_id": SETTING_GARBAGE_CHECKPOINT
+                },
+                doc! {
+                    "$set": {
+                        "value": {
+                            "phase": phase,
+                            "last_id": last_id
+                        }
+                    }
+                },
+                Some(options),
+            )
+            .await?;
 
-        let expire = DateTime::from_millis(now.timestamp_millis() + TEMPORARY_LIFE_TIME);
-        let expire_chunks =
-            DateTime::from_millis(now.timestamp_millis() + TEMPORARY_CHUNK_LIFE_TIME);
+        Ok(())
+    }
 
-        file_item_raw.insert("create_time", now);
+    /// Remove what file items which are being removed add to the usage counters of their owners.
+    async fn remove_garbage_items_usage(&self, ids: &[ObjectId]) -> Result<(), FileCenterError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
 
-        file_item_raw.insert("expire_at", expire);
+        let mut options = FindOptions::default();
+        options.projection = Some(file_item_delete_projection());
 
-        if is_stream {
-            self.collections
-                .files_chunks
-                .update_many(
-                    doc! {
-                        "file_id": file_id
-                    },
-                    doc! {
-                        "$set": {
-                            "expire_at": expire_chunks
-                        }
+        let mut result = self
+            .collections
+            .files
+            .find(
+                doc! {
+                    "_id": {
+                        "$in": ids
                     },
-                    None,
-                )
-                .await?;
-        }
+                    "owner": {
+                        "$exists": true
+                    }
+                },
+                options,
+            )
+            .await?;
 
-        self.collections.files.insert_one(file_item_raw, None).await?;
+        while let Some(file_item) = result.try_next().await? {
+            self.remove_file_item_usage(&file_item, &mut Session::none()).await?;
+        }
 
-        Ok(file_id)
+        Ok(())
     }
-}
 
-impl FileCenter {
-    /// Remove all unused file meta and file chunks in this file center.
-    pub async fn clear_garbage(&self) -> Result<(), FileCenterError> {
-        // unnecessary file items which have chunk_id but the target chunks do not exist
-        {
-            let mut result = self
-                .collections
-                .files
-                .aggregate(
-                    [
-                        doc! {
-                            "$match": {
-                                "chunk_id": {
-                                    "$exists": true
-                                }
-                            }
-                        },
-                        doc! {
-                            "$lookup": {
-                             "from": COLLECTION_FILES_CHUNKS_NAME,
-                             "localField": "chunk_id",
-                             "foreignField": "_id",
-                             "as": "chunk"
-                           }
-                        },
-                        doc! {
-                            "$match": {
-                                "chunk": []
-                            }
-                        },
-                        doc! {
-                            "$project": {
-                                "_id": 1
-                            }
-                        },
-                    ],
-                    None,
-                )
-                .await?;
+    /// Remove file items, and their chunks if `with_chunks` is `true`, and count what is removed.
+    async fn remove_garbage_items(
+        &self,
+        ids: &[ObjectId],
+        with_chunks: bool,
+        dry_run: bool,
+        report: &mut GarbageReport,
+    ) -> Result<(), FileCenterError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
 
-            let mut ids = Vec::new();
+        let (_, bytes) =
+            self.sum_data_size(&self.collections.files, "_id", ids, "file_data").await?;
 
-            while let Some(d) = result.try_next().await? {
-                ids.push(d.get_object_id("_id")?);
-            }
+        report.reclaimed_bytes += bytes;
 
-            if !ids.is_empty() {
-                self.collections
-                    .files
-                    .delete_many(
-                        doc! {
-                                "_id": {
-                                    "$in": ids
-                            }
-                        },
-                        None,
-                    )
-                    .await?;
-            }
+        if with_chunks {
+            let (chunks, bytes) =
+                self.sum_data_size(&self.collections.files_chunks, "file_id", ids, "data").await?;
+
+            report.removed_chunks += chunks;
+            report.reclaimed_bytes += bytes;
         }
 
-        // unnecessary file items whose count are smaller than or equal to 0
-        {
-            let mut result = self
-                .collections
+        if !dry_run {
+            self.collections
                 .files
-                .find(
+                .delete_many(
                     doc! {
-                        "count": {
-                            "$lte": 0
+                            "_id": {
+                                "$in": ids
                         }
                     },
                     None,
                 )
                 .await?;
 
-            let mut ids = Vec::new();
-
-            while let Some(d) = result.try_next().await? {
-                ids.push(d.get_object_id("_id")?);
-            }
-
-            if !ids.is_empty() {
-                self.collections
-                    .files
-                    .delete_many(
-                        doc! {
-                                "_id": {
-                                    "$in": ids.clone()
-                            }
-                        },
-                        None,
-                    )
-                    .await?;
-
+            if with_chunks {
                 self.collections
                     .files_chunks
                     .delete_many(
@@ -1752,69 +4210,88 @@ impl FileCenter {
             }
         }
 
-        // unnecessary chunks which are not used in file items
-        {
-            let mut result = self
-                .collections
+        Ok(())
+    }
+
+    /// Remove the chunks of files, and count what is removed.
+    async fn remove_garbage_chunks(
+        &self,
+        file_ids: &[ObjectId],
+        dry_run: bool,
+        report: &mut GarbageReport,
+    ) -> Result<(), FileCenterError> {
+        if file_ids.is_empty() {
+            return Ok(());
+        }
+
+        let (chunks, bytes) =
+            self.sum_data_size(&self.collections.files_chunks, "file_id", file_ids, "data").await?;
+
+        report.removed_chunks += chunks;
+        report.reclaimed_bytes += bytes;
+
+        if !dry_run {
+            self.collections
                 .files_chunks
-                .aggregate(
-                    [
-                        doc! {
-                            "$lookup": {
-                             "from": COLLECTION_FILES_NAME,
-                             "localField": "file_id",
-                             "foreignField": "_id",
-                             "as": "item"
-                           }
-                        },
-                        doc! {
-                            "$match": {
-                                "item": []
-                            }
-                        },
-                        doc! {
-                            "$group": {
-                                "_id": null,
-                                "file_ids": {
-                                    "$addToSet": "$file_id"
-                                }
-                            }
-                        },
-                        doc! {
-                            "$unwind": "$file_ids"
-                        },
-                        doc! {
-                            "$project": {
-                                "file_id": "$file_ids"
-                            }
-                        },
-                    ],
+                .delete_many(
+                    doc! {
+                            "file_id": {
+                                "$in": file_ids
+                        }
+                    },
                     None,
                 )
                 .await?;
+        }
 
-            let mut ids = Vec::new();
-
-            while let Some(d) = result.try_next().await? {
-                ids.push(d.get_object_id("file_id")?);
-            }
+        Ok(())
+    }
 
-            if !ids.is_empty() {
-                self.collections
-                    .files_chunks
-                    .delete_many(
-                        doc! {
-                                "file_id": {
-                                    "$in": ids
+    /// Count the documents whose `key` is in `ids`, and sum the sizes of their binary `field`.
+    async fn sum_data_size(
+        &self,
+        collection: &Collection<Document>,
+        key: &str,
+        ids: &[ObjectId],
+        field: &str,
+    ) -> Result<(u64, u64), FileCenterError> {
+        let mut result = collection
+            .aggregate(
+                [
+                    doc! {
+                        "$match": {
+                            key: {
+                                "$in": ids
                             }
-                        },
-                        None,
-                    )
-                    .await?;
-            }
-        }
+                        }
+                    },
+                    doc! {
+                        "$group": {
+                            "_id": null,
+                            "count": {
+                                "$sum": 1
+                            },
+                            "bytes": {
+                                "$sum": {
+                                    "$binarySize": format!("${}", field)
+                                }
+                            }
+                        }
+                    },
+                ],
+                None,
+            )
+            .await?;
 
-        Ok(())
+        match result.try_next().await? {
+            Some(d) => {
+                let count = d.get("count").and_then(bson_to_u64).unwrap_or(0);
+                let bytes = d.get("bytes").and_then(bson_to_u64).unwrap_or(0);
+
+                Ok((count, bytes))
+            },
+            None => Ok((0, 0)),
+        }
     }
 }
 
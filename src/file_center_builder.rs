@@ -0,0 +1,265 @@
+use std::time::Duration;
+
+use crate::{
+    bson::Document,
+    file_center::FileCenterCollections,
+    mongodb::{
+        options::{ClientOptions, CollectionOptions, ReadConcern, WriteConcern},
+        Client, Collection, Database,
+    },
+    FileCenter, FileCenterError, COLLECTION_FILES_CHUNKS_NAME, COLLECTION_FILES_NAME,
+    COLLECTION_QUOTAS_NAME, COLLECTION_SETTINGS_NAME, DEFAULT_CHUNK_SIZE, DEFAULT_DATABASE_NAME,
+    DEFAULT_FILE_SIZE_THRESHOLD, DEFAULT_TEMPORARY_LIFE_TIME, MAX_CHUNK_SIZE,
+    MAX_FILE_SIZE_THRESHOLD,
+};
+
+#[derive(Debug, Clone)]
+enum FileCenterSource {
+    Uri(String),
+    Client(Client),
+    Database(Database),
+}
+
+/// To configure and create a `FileCenter` instance.
+///
+/// The initial values of the file size threshold, the chunk size and the temporary life time are only stored when the file center is created for the first time. After that, the values stored in the database are used, and they can be changed by the setters of `FileCenter`.
+#[derive(Debug, Clone)]
+pub struct FileCenterBuilder {
+    source:                       FileCenterSource,
+    database_name:                Option<String>,
+    files_collection_name:        String,
+    files_chunks_collection_name: String,
+    settings_collection_name:     String,
+    quotas_collection_name:       String,
+    collection_prefix:            String,
+    bucket:                       Option<String>,
+    file_size_threshold:          u32,
+    chunk_size:                   u32,
+    temporary_life_time:          Duration,
+    read_concern:                 Option<ReadConcern>,
+    write_concern:                Option<WriteConcern>,
+    create_indexes:               bool,
+}
+
+impl FileCenterBuilder {
+    #[inline]
+    fn new(source: FileCenterSource) -> FileCenterBuilder {
+        FileCenterBuilder {
+            source,
+            database_name: None,
+            files_collection_name: COLLECTION_FILES_NAME.to_string(),
+            files_chunks_collection_name: COLLECTION_FILES_CHUNKS_NAME.to_string(),
+            settings_collection_name: COLLECTION_SETTINGS_NAME.to_string(),
+            quotas_collection_name: COLLECTION_QUOTAS_NAME.to_string(),
+            collection_prefix: String::new(),
+            bucket: None,
+            file_size_threshold: DEFAULT_FILE_SIZE_THRESHOLD,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            temporary_life_time: DEFAULT_TEMPORARY_LIFE_TIME,
+            read_concern: None,
+            write_concern: None,
+            create_indexes: true,
+        }
+    }
+
+    /// Connect to MongoDB with a URI. The database in the URI is used, or `DEFAULT_DATABASE_NAME` if there is none.
+    #[inline]
+    pub fn from_uri<U: AsRef<str>>(uri: U) -> FileCenterBuilder {
+        Self::new(FileCenterSource::Uri(uri.as_ref().to_string()))
+    }
+
+    /// Use an existing client. The default database of the client is used, or `DEFAULT_DATABASE_NAME` if there is none.
+    #[inline]
+    pub fn from_client(client: Client) -> FileCenterBuilder {
+        Self::new(FileCenterSource::Client(client))
+    }
+
+    /// Use an existing database.
+    #[inline]
+    pub fn from_database(db: Database) -> FileCenterBuilder {
+        Self::new(FileCenterSource::Database(db))
+    }
+
+    /// Set the name of the database, instead of the one in the URI or the default database of the client. It is ignored if the builder is created from a database.
+    #[inline]
+    pub fn database_name<S: Into<String>>(mut self, database_name: S) -> Self {
+        self.database_name = Some(database_name.into());
+
+        self
+    }
+
+    /// Set the name of the collection which stores file items. The default one is `COLLECTION_FILES_NAME`.
+    #[inline]
+    pub fn files_collection_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.files_collection_name = name.into();
+
+        self
+    }
+
+    /// Set the name of the collection which stores file chunks. The default one is `COLLECTION_FILES_CHUNKS_NAME`.
+    #[inline]
+    pub fn files_chunks_collection_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.files_chunks_collection_name = name.into();
+
+        self
+    }
+
+    /// Set the name of the collection which stores the settings. The default one is `COLLECTION_SETTINGS_NAME`.
+    #[inline]
+    pub fn settings_collection_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.settings_collection_name = name.into();
+
+        self
+    }
+
+    /// Set the name of the collection which stores the quotas and the usage counters of owners. The default one is `COLLECTION_QUOTAS_NAME`.
+    #[inline]
+    pub fn quotas_collection_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.quotas_collection_name = name.into();
+
+        self
+    }
+
+    /// Set a prefix which is prepended to the names of all the collections, so that multiple file centers can be stored in one database.
+    #[inline]
+    pub fn collection_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.collection_prefix = prefix.into();
+
+        self
+    }
+
+    /// Use the collections of a bucket, whose names are prefixed with `"<name>_"` after the collection prefix. The name must not be empty or contain `$` or the null character. See `FileCenter::bucket`.
+    #[inline]
+    pub fn bucket<S: Into<String>>(mut self, name: S) -> Self {
+        self.bucket = Some(name.into());
+
+        self
+    }
+
+    /// Set the initial file size threshold.
+    #[inline]
+    pub fn file_size_threshold(mut self, file_size_threshold: u32) -> Self {
+        self.file_size_threshold = file_size_threshold;
+
+        self
+    }
+
+    /// Set the initial chunk size.
+    #[inline]
+    pub fn chunk_size(mut self, chunk_size: u32) -> Self {
+        self.chunk_size = chunk_size;
+
+        self
+    }
+
+    /// Set the initial default life time of temporary files.
+    #[inline]
+    pub fn temporary_life_time(mut self, temporary_life_time: Duration) -> Self {
+        self.temporary_life_time = temporary_life_time;
+
+        self
+    }
+
+    /// Set the read concern of all the collections. If it is not set, the one of the database is used.
+    #[inline]
+    pub fn read_concern(mut self, read_concern: ReadConcern) -> Self {
+        self.read_concern = Some(read_concern);
+
+        self
+    }
+
+    /// Set the write concern of all the collections. If it is not set, the one of the database is used.
+    #[inline]
+    pub fn write_concern(mut self, write_concern: WriteConcern) -> Self {
+        self.write_concern = Some(write_concern);
+
+        self
+    }
+
+    /// Set whether to create the indexes when the file center is created. It is enabled by default. If it is disabled, the indexes have to be created in another way. Without the TTL indexes, expired temporary files are not removed automatically, but `FileCenter::delete_expired_files` can still remove them.
+    #[inline]
+    pub fn create_indexes(mut self, create_indexes: bool) -> Self {
+        self.create_indexes = create_indexes;
+
+        self
+    }
+
+    /// Create the `FileCenter` instance.
+    pub async fn build(self) -> Result<FileCenter, FileCenterError> {
+        if self.file_size_threshold > MAX_FILE_SIZE_THRESHOLD || self.file_size_threshold == 0 {
+            return Err(FileCenterError::FileSizeThresholdError);
+        }
+
+        if self.chunk_size > MAX_CHUNK_SIZE || self.chunk_size == 0 {
+            return Err(FileCenterError::ChunkSizeError);
+        }
+
+        let millis = self.temporary_life_time.as_millis();
+
+        if millis == 0 || millis > i64::MAX as u128 {
+            return Err(FileCenterError::TemporaryLifeTimeError);
+        }
+
+        let collection_prefix = match self.bucket {
+            Some(bucket) => {
+                if bucket.is_empty() || bucket.contains(['$', '\0']) {
+                    return Err(FileCenterError::BucketNameError);
+                }
+
+                format!("{}{}_", self.collection_prefix, bucket)
+            },
+            None => self.collection_prefix,
+        };
+
+        let db = match self.source {
+            FileCenterSource::Uri(uri) => {
+                let client_options = ClientOptions::parse(&uri).await?;
+
+                let database_name = self
+                    .database_name
+                    .or_else(|| client_options.default_database.clone())
+                    .unwrap_or_else(|| DEFAULT_DATABASE_NAME.to_string());
+
+                let client = Client::with_options(client_options)?;
+
+                client.database(&database_name)
+            },
+            FileCenterSource::Client(client) => match self.database_name.as_deref() {
+                Some(database_name) => client.database(database_name),
+                None => client
+                    .default_database()
+                    .unwrap_or_else(|| client.database(DEFAULT_DATABASE_NAME)),
+            },
+            FileCenterSource::Database(db) => db,
+        };
+
+        let mut collection_options = CollectionOptions::default();
+        collection_options.read_concern = self.read_concern;
+        collection_options.write_concern = self.write_concern;
+
+        let collection = |name: &str| -> Collection<Document> {
+            db.collection_with_options(
+                &format!("{}{}", collection_prefix, name),
+                collection_options.clone(),
+            )
+        };
+
+        let collections = FileCenterCollections {
+            files:        collection(&self.files_collection_name),
+            files_chunks: collection(&self.files_chunks_collection_name),
+            settings:     collection(&self.settings_collection_name),
+            quotas:       collection(&self.quotas_collection_name),
+            prefix:       collection_prefix,
+        };
+
+        FileCenter::open(
+            db,
+            collections,
+            self.file_size_threshold,
+            self.chunk_size,
+            self.temporary_life_time,
+            self.create_indexes,
+        )
+        .await
+    }
+}
@@ -4,13 +4,23 @@ use std::{
     io,
 };
 
+use crate::IntegrityError;
+
 #[derive(Debug)]
 pub enum FileCenterError {
     MongoDBError(crate::mongodb::error::Error),
     DocumentError(crate::bson::document::ValueAccessError),
     FileSizeThresholdError,
+    ChunkSizeError,
+    TemporaryLifeTimeError,
+    BucketNameError,
+    TransactionsNotSupportedError,
     VersionError,
     DatabaseTooNewError { supported_latest: i32, current: i32 },
+    EncryptionKeyNotFoundError(String),
+    QuotaExceeded { owner: String },
+    MetadataTooLargeError,
+    IntegrityError(IntegrityError),
     IOError(io::Error),
     IDTokenError(&'static str),
 }
@@ -24,6 +34,14 @@ impl Display for FileCenterError {
             FileCenterError::FileSizeThresholdError => {
                 f.write_str("the file size threshold is incorrect")
             },
+            FileCenterError::ChunkSizeError => f.write_str("the chunk size is incorrect"),
+            FileCenterError::TemporaryLifeTimeError => {
+                f.write_str("the temporary life time is incorrect")
+            },
+            FileCenterError::BucketNameError => f.write_str("the bucket name is incorrect"),
+            FileCenterError::TransactionsNotSupportedError => {
+                f.write_str("transactions are not supported by the MongoDB deployment")
+            },
             FileCenterError::VersionError => f.write_str("the version is incorrect"),
             FileCenterError::DatabaseTooNewError {
                 supported_latest,
@@ -32,6 +50,16 @@ impl Display for FileCenterError {
                 "the current database version is {}, but this library only supports to {}",
                 current, supported_latest
             )),
+            FileCenterError::EncryptionKeyNotFoundError(key_id) => {
+                f.write_fmt(format_args!("the encryption key {:?} is not found", key_id))
+            },
+            FileCenterError::QuotaExceeded {
+                owner,
+            } => f.write_fmt(format_args!("the quota of the owner {:?} is exceeded", owner)),
+            FileCenterError::MetadataTooLargeError => {
+                f.write_str("the metadata and the file name are too large to be stored")
+            },
+            FileCenterError::IntegrityError(err) => Display::fmt(err, f),
             FileCenterError::IOError(err) => Display::fmt(err, f),
             FileCenterError::IDTokenError(err) => f.write_str(err),
         }
@@ -57,6 +85,13 @@ impl From<crate::bson::document::ValueAccessError> for FileCenterError {
 impl From<io::Error> for FileCenterError {
     #[inline]
     fn from(err: io::Error) -> Self {
+        // an integrity error which is wrapped by a stream of file data
+        if err.get_ref().map_or(false, |inner| inner.is::<IntegrityError>()) {
+            let inner = err.into_inner().unwrap().downcast::<IntegrityError>().unwrap();
+
+            return FileCenterError::IntegrityError(*inner);
+        }
+
         FileCenterError::IOError(err)
     }
 }
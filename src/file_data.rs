@@ -1,16 +1,23 @@
-use std::io::{self, Cursor};
+use std::{
+    io::{self, Cursor},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
 
-use crate::tokio_stream::{Stream, StreamExt};
+use crate::{
+    tokio::io::{AsyncRead, ReadBuf},
+    tokio_stream::{Stream, StreamExt},
+};
+
+/// The stream of file chunks retrieved from MongoDB.
+pub type FileDataStream = Box<dyn Stream<Item = Result<Cursor<Vec<u8>>, io::Error>> + Unpin + Send>;
 
 /// To represent the file data retrieved from MongoDB.
 #[derive(Educe)]
 #[educe(Debug)]
 pub enum FileData {
     Buffer(Vec<u8>),
-    Stream(
-        #[educe(Debug(ignore))]
-        Box<dyn Stream<Item = Result<Cursor<Vec<u8>>, io::Error>> + Unpin + Send>,
-    ),
+    Stream(#[educe(Debug(ignore))] FileDataStream),
 }
 
 impl FileData {
@@ -32,4 +39,65 @@ impl FileData {
             },
         }
     }
+
+    /// Turn into a `FileDataReader` instance, which implements `AsyncRead`.
+    #[inline]
+    pub fn into_async_read(self) -> FileDataReader {
+        match self {
+            FileData::Buffer(v) => FileDataReader {
+                stream: None, current: Cursor::new(v)
+            },
+            FileData::Stream(f) => FileDataReader {
+                stream:  Some(f),
+                current: Cursor::new(Vec::new()),
+            },
+        }
+    }
+}
+
+/// To read the file data via `AsyncRead`.
+#[derive(Educe)]
+#[educe(Debug)]
+pub struct FileDataReader {
+    #[educe(Debug(ignore))]
+    stream:  Option<FileDataStream>,
+    current: Cursor<Vec<u8>>,
+}
+
+impl AsyncRead for FileDataReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+
+        loop {
+            let position = this.current.position() as usize;
+            let data = this.current.get_ref();
+
+            if position < data.len() {
+                let c = buf.remaining().min(data.len() - position);
+
+                buf.put_slice(&data[position..position + c]);
+
+                this.current.set_position((position + c) as u64);
+
+                return Poll::Ready(Ok(()));
+            }
+
+            match this.stream.as_mut() {
+                Some(stream) => match ready!(Pin::new(stream).poll_next(cx)) {
+                    Some(Ok(chunk)) => this.current = chunk,
+                    Some(Err(err)) => return Poll::Ready(Err(err)),
+                    None => {
+                        this.stream = None;
+
+                        return Poll::Ready(Ok(()));
+                    },
+                },
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
 }
@@ -0,0 +1,60 @@
+use crate::{
+    bson::{oid::ObjectId, DateTime, Document},
+    mime::Mime,
+    ContentEncoding,
+};
+
+/// To represent the metadata of a file retrieved from MongoDB, without its data.
+#[derive(Debug, Clone)]
+pub struct FileInfo {
+    pub(crate) file_id:          ObjectId,
+    pub(crate) create_time:      DateTime,
+    pub(crate) expire_at:        Option<DateTime>,
+    pub(crate) mime_type:        Mime,
+    pub(crate) file_size:        u64,
+    pub(crate) file_name:        String,
+    pub(crate) metadata:         Option<Document>,
+    pub(crate) count:            i32,
+    pub(crate) content_encoding: Option<ContentEncoding>,
+}
+
+impl FileInfo {
+    pub fn get_file_id(&self) -> ObjectId {
+        self.file_id
+    }
+
+    pub fn get_create_time(&self) -> DateTime {
+        self.create_time
+    }
+
+    pub fn get_expiration_time(&self) -> Option<DateTime> {
+        self.expire_at
+    }
+
+    pub fn get_mime_type(&self) -> &Mime {
+        &self.mime_type
+    }
+
+    pub fn get_file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    pub fn get_file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// Get the user-defined metadata of the file.
+    pub fn get_metadata(&self) -> Option<&Document> {
+        self.metadata.as_ref()
+    }
+
+    /// Get the reference count of the file.
+    pub fn get_count(&self) -> i32 {
+        self.count
+    }
+
+    /// Get the encoding which the stored data is compressed with.
+    pub fn get_content_encoding(&self) -> Option<ContentEncoding> {
+        self.content_encoding
+    }
+}
@@ -1,20 +1,24 @@
 use crate::{
-    bson::{oid::ObjectId, DateTime},
+    bson::{oid::ObjectId, DateTime, Document},
+    integrity::HashVerifier,
     mime::Mime,
-    FileData,
+    ContentEncoding, FileData,
 };
 
 /// To represent the file retrieved from MongoDB.
 #[derive(Educe)]
 #[educe(Debug)]
 pub struct FileItem {
-    pub(crate) file_id:     ObjectId,
-    pub(crate) create_time: DateTime,
-    pub(crate) expire_at:   Option<DateTime>,
-    pub(crate) mime_type:   Mime,
-    pub(crate) file_size:   u64,
-    pub(crate) file_name:   String,
-    pub(crate) file_data:   FileData,
+    pub(crate) file_id:          ObjectId,
+    pub(crate) create_time:      DateTime,
+    pub(crate) expire_at:        Option<DateTime>,
+    pub(crate) mime_type:        Mime,
+    pub(crate) file_size:        u64,
+    pub(crate) file_name:        String,
+    pub(crate) metadata:         Option<Document>,
+    pub(crate) content_encoding: Option<ContentEncoding>,
+    pub(crate) hash:             Option<(i64, i64, i64, i64)>,
+    pub(crate) file_data:        FileData,
 }
 
 impl FileItem {
@@ -42,7 +46,35 @@ impl FileItem {
         &self.file_name
     }
 
+    /// Get the user-defined metadata of the file.
+    pub fn get_metadata(&self) -> Option<&Document> {
+        self.metadata.as_ref()
+    }
+
+    /// Get the encoding which the stored data is compressed with.
+    pub fn get_content_encoding(&self) -> Option<ContentEncoding> {
+        self.content_encoding
+    }
+
+    /// Turn into the file data. It is decompressed if it has been compressed.
+    ///
+    /// If the data is a stream, its size and its SHA-256 hash (only for perennial files) are checked after it is read to the end. A mismatch is returned as an `IntegrityError` wrapped in `io::Error`.
     pub fn into_file_data(self) -> FileData {
+        let file_data = match self.content_encoding {
+            Some(content_encoding) => content_encoding.decode_file_data(self.file_data),
+            None => self.file_data,
+        };
+
+        match file_data {
+            FileData::Stream(stream) => {
+                FileData::Stream(Box::new(HashVerifier::new(stream, self.file_size, self.hash)))
+            },
+            FileData::Buffer(_) => file_data,
+        }
+    }
+
+    /// Turn into the file data as it is stored, which is compressed with the encoding returned by `get_content_encoding`. It can be sent along with the `Content-Encoding` header of HTTP.
+    pub fn into_raw_file_data(self) -> FileData {
         self.file_data
     }
 }
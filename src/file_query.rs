@@ -0,0 +1,33 @@
+use crate::{
+    bson::{oid::ObjectId, DateTime, Document},
+    mime::Mime,
+};
+
+/// The conditions used to list file items. Every condition which is set must be satisfied. Expired temporary files are never listed.
+///
+/// The results are sorted by their Object IDs. To fetch the next page, set `after` to the ID of the last file of the current page.
+#[derive(Debug, Clone, Default)]
+pub struct FileQuery {
+    /// The exact MIME type.
+    pub mime_type:         Option<Mime>,
+    /// The prefix of the MIME type, such as `"image/"`.
+    pub mime_type_prefix:  Option<String>,
+    /// A regular expression (in the MongoDB syntax) which the file name should match.
+    pub file_name_pattern: Option<String>,
+    /// The minimum file size (inclusive).
+    pub min_file_size:     Option<u64>,
+    /// The maximum file size (inclusive).
+    pub max_file_size:     Option<u64>,
+    /// The earliest creation time (inclusive).
+    pub created_after:     Option<DateTime>,
+    /// The latest creation time (exclusive).
+    pub created_before:    Option<DateTime>,
+    /// The fields which the user-defined metadata should contain. For example, `doc! { "owner": "alice" }`.
+    pub metadata:          Option<Document>,
+    /// `Some(true)` to list only temporary files, `Some(false)` to list only perennial files.
+    pub temporary:         Option<bool>,
+    /// Only list files whose Object IDs are greater than this one.
+    pub after:             Option<ObjectId>,
+    /// The maximum number of files to list.
+    pub limit:             Option<u32>,
+}
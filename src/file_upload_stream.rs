@@ -0,0 +1,364 @@
+use std::{
+    future::Future,
+    io::{self, ErrorKind},
+    mem,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use crate::{
+    bson::oid::ObjectId,
+    data_encoder::DataEncoder,
+    file_center::{chunk_document, insert_chunk, insert_chunks},
+    functions::separate_hash,
+    mime::Mime,
+    session::Session,
+    tokio::io::AsyncWrite,
+    Digest, FileCenter, FileCenterError, Hasher, UploadOptions, DEFAULT_MIME_TYPE,
+};
+
+type ChunkFuture =
+    Pin<Box<dyn Future<Output = (Option<Session>, Result<ObjectId, FileCenterError>)> + Send>>;
+
+/// A writer which uploads a file to the file center. Call `finish` after all the data is written to store the file item. If it is dropped without being finished, the chunks which have been uploaded will be removed by `clear_garbage`, or discarded immediately if transactions are enabled.
+#[derive(Educe)]
+#[educe(Debug)]
+pub struct FileUploadStream<'a> {
+    #[educe(Debug(ignore))]
+    file_center:    &'a FileCenter,
+    file_id:        ObjectId,
+    file_name:      String,
+    mime_type:      Mime,
+    upload_options: UploadOptions,
+    encoder:        DataEncoder,
+    temporary:      bool,
+    #[educe(Debug(ignore))]
+    hasher:         Hasher,
+    threshold:      usize,
+    chunk_size:     usize,
+    streaming:      bool,
+    buffer:         Vec<u8>,
+    n:              i64,
+    file_size:      u64,
+    budget:         Option<u64>,
+    #[educe(Debug(ignore))]
+    pending:        Option<ChunkFuture>,
+    #[educe(Debug(ignore))]
+    session:        Option<Session>,
+}
+
+impl<'a> FileUploadStream<'a> {
+    pub(crate) fn new(
+        file_center: &'a FileCenter,
+        file_name: String,
+        mime_type: Option<Mime>,
+        upload_options: UploadOptions,
+        temporary: bool,
+        threshold: u64,
+        budget: Option<u64>,
+    ) -> FileUploadStream<'a> {
+        let mime_type = mime_type.unwrap_or(DEFAULT_MIME_TYPE);
+        let encoder = file_center.get_data_encoder(&upload_options, &mime_type);
+
+        FileUploadStream {
+            file_center,
+            file_id: ObjectId::new(),
+            file_name,
+            mime_type,
+            upload_options,
+            encoder,
+            temporary,
+            hasher: Hasher::new(),
+            threshold: threshold as usize,
+            chunk_size: file_center.get_chunk_size() as usize,
+            streaming: false,
+            buffer: Vec::new(),
+            n: 0,
+            file_size: 0,
+            budget,
+            pending: None,
+            session: None,
+        }
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        if let Some(pending) = self.pending.as_mut() {
+            let (session, result) = ready!(pending.as_mut().poll(cx));
+
+            self.pending = None;
+            self.session = session;
+
+            if let Err(err) = result {
+                return Poll::Ready(Err(io::Error::new(ErrorKind::Other, err)));
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Store the full chunks in the buffer, and keep the rest of the data in the buffer.
+    fn start_chunks(&mut self) {
+        self.streaming = true;
+
+        let bytes = mem::take(&mut self.buffer);
+
+        let mut chunks = Vec::new();
+
+        if bytes.len() == self.chunk_size {
+            chunks.push(bytes);
+        } else {
+            let mut exact_chunks = bytes.chunks_exact(self.chunk_size);
+
+            for chunk in exact_chunks.by_ref() {
+                chunks.push(chunk.to_vec());
+            }
+
+            self.buffer.extend_from_slice(exact_chunks.remainder());
+        }
+
+        self.buffer.reserve(self.chunk_size - self.buffer.len());
+
+        if chunks.is_empty() {
+            return;
+        }
+
+        let file_id = self.file_id;
+        let n = self.n;
+
+        self.n += chunks.len() as i64;
+
+        let encoder = self.encoder.clone();
+        let collection_files_chunks = self.file_center.collections.files_chunks.clone();
+        let transactions = self.file_center.is_transactions_enabled();
+        let session = self.session.take();
+
+        self.pending = Some(Box::pin(async move {
+            let mut batch = Vec::with_capacity(chunks.len());
+
+            for (i, chunk) in chunks.into_iter().enumerate() {
+                let chunk = match encoder.encode_chunk(file_id, n + i as i64, chunk) {
+                    Ok(chunk) => chunk,
+                    Err(err) => return (session, Err(err.into())),
+                };
+
+                batch.push(chunk_document(file_id, n + i as i64, chunk));
+            }
+
+            // the session is started along with the first chunk
+            let mut session = match session {
+                Some(session) => session,
+                None => {
+                    match Session::start(collection_files_chunks.client(), transactions).await {
+                        Ok(session) => session,
+                        Err(err) => return (None, Err(err.into())),
+                    }
+                },
+            };
+
+            let result = insert_chunks(&collection_files_chunks, &mut session, batch).await;
+
+            (Some(session), result)
+        }));
+    }
+
+    /// The maximum size of the buffer. Before the data is separated into chunks, the buffer holds up to `threshold` bytes, which may be stored inline.
+    #[inline]
+    fn capacity(&self) -> usize {
+        if self.streaming {
+            self.chunk_size
+        } else {
+            self.threshold
+        }
+    }
+
+    async fn wait_pending(&mut self) -> Result<(), FileCenterError> {
+        if let Some(pending) = self.pending.take() {
+            let (session, result) = pending.await;
+
+            self.session = session;
+
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the ID of the file which is being uploaded. A different ID may be returned by `finish` if the file is perennial.
+    #[inline]
+    pub fn get_file_id(&self) -> ObjectId {
+        self.file_id
+    }
+
+    /// Store the file item after all the data is written, and get its ID.
+    pub async fn finish(mut self) -> Result<ObjectId, FileCenterError> {
+        let file_center = self.file_center;
+        let file_id = self.file_id;
+
+        // the data is not bigger than the threshold if it has not been separated into chunks
+        let is_stream = self.streaming;
+
+        let pending_result = self.wait_pending().await;
+
+        let mut session = match self.session.take() {
+            Some(session) => session,
+            None => file_center.start_session().await?,
+        };
+
+        let result = match pending_result {
+            Ok(()) => self.store(is_stream, &mut session).await,
+            Err(err) => Err(err),
+        };
+
+        if result.is_err()
+            && is_stream
+            && file_center.delete_file_chunks(file_id, &mut session).await.is_err()
+        {
+            // do nothing
+        }
+
+        session.end(result).await
+    }
+
+    async fn store(
+        mut self,
+        is_stream: bool,
+        session: &mut Session,
+    ) -> Result<ObjectId, FileCenterError> {
+        let file_center = self.file_center;
+        let file_id = self.file_id;
+
+        let mut file_item_raw = doc! {
+            "_id": file_id,
+            "file_size": self.file_size as i64,
+            "count": 1i32
+        };
+
+        if is_stream {
+            let chunk = self.encoder.encode_chunk(file_id, self.n, mem::take(&mut self.buffer))?;
+
+            let chunk_id = insert_chunk(
+                &file_center.collections.files_chunks,
+                session,
+                file_id,
+                self.n,
+                chunk,
+            )
+            .await?;
+
+            file_item_raw.insert("chunk_id", chunk_id);
+            file_item_raw.insert("chunk_size", self.chunk_size as i32);
+
+            self.encoder.insert_chunk_fields(&mut file_item_raw);
+        } else {
+            self.encoder.insert_file_data(
+                &mut file_item_raw,
+                file_id,
+                mem::take(&mut self.buffer),
+            )?;
+        }
+
+        if self.temporary {
+            file_center
+                .insert_temporary_file_item(
+                    file_item_raw,
+                    self.file_name,
+                    self.mime_type,
+                    self.upload_options,
+                    session,
+                )
+                .await
+        } else {
+            let hash = separate_hash(&self.hasher.finalize());
+
+            file_center
+                .insert_file_item_with_hash(
+                    file_item_raw,
+                    hash,
+                    self.file_name,
+                    self.mime_type,
+                    self.upload_options,
+                    session,
+                )
+                .await
+        }
+    }
+
+    /// Cancel the upload and remove the chunks which have been uploaded.
+    pub async fn abort(mut self) -> Result<(), FileCenterError> {
+        if self.wait_pending().await.is_err() {
+            // do nothing
+        }
+
+        if let Some(session) = self.session.take() {
+            session.abort().await;
+        }
+
+        if self.n > 0 {
+            self.file_center.delete_file_chunks(self.file_id, &mut Session::none()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> AsyncWrite for FileUploadStream<'a> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_pending(cx))?;
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if this.buffer.len() == this.capacity() {
+            // more data is coming, so the full chunks in the buffer have to be stored
+            this.start_chunks();
+
+            ready!(this.poll_pending(cx))?;
+        }
+
+        let mut c = buf.len().min(this.capacity() - this.buffer.len());
+
+        // the data which the owner cannot put is rejected before it is stored
+        if let Some(budget) = this.budget {
+            let remaining = budget.saturating_sub(this.file_size);
+
+            if remaining == 0 {
+                let err = FileCenterError::QuotaExceeded {
+                    owner: this.upload_options.owner.clone().unwrap_or_default(),
+                };
+
+                return Poll::Ready(Err(io::Error::new(ErrorKind::Other, err)));
+            }
+
+            c = c.min(remaining.min(usize::MAX as u64) as usize);
+        }
+
+        let data = &buf[..c];
+
+        this.buffer.extend_from_slice(data);
+
+        if !this.temporary {
+            this.hasher.update(data);
+        }
+
+        this.file_size += c as u64;
+
+        Poll::Ready(Ok(c))
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        self.get_mut().poll_pending(cx)
+    }
+}
@@ -1,4 +1,4 @@
-use std::{mem::transmute, path::Path};
+use std::path::Path;
 
 use crate::{
     mime::Mime,
@@ -20,6 +20,22 @@ pub(crate) fn get_mime_by_path<P: AsRef<Path>>(file_path: P) -> Mime {
     }
 }
 
+pub(crate) fn escape_regex<S: AsRef<str>>(s: S) -> String {
+    let s = s.as_ref();
+
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        if "\\^$.|?*+()[]{}/".contains(c) {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
 pub(crate) async fn get_hash_by_path<P: AsRef<Path>>(
     file_path: P,
 ) -> Result<(i64, i64, i64, i64), io::Error> {
@@ -70,9 +86,9 @@ pub(crate) fn separate_hash(hash: &[u8]) -> (i64, i64, i64, i64) {
     hash_4.copy_from_slice(&hash[24..32]);
 
     (
-        unsafe { transmute(hash_1) },
-        unsafe { transmute(hash_2) },
-        unsafe { transmute(hash_3) },
-        unsafe { transmute(hash_4) },
+        i64::from_ne_bytes(hash_1),
+        i64::from_ne_bytes(hash_2),
+        i64::from_ne_bytes(hash_3),
+        i64::from_ne_bytes(hash_4),
     )
 }
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+/// How `FileCenter::clear_garbage_with_options` removes the garbage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GarbageOptions {
+    /// The chunks of a file are not removed until this period has passed since the last one of them was inserted. It has to be longer than the longest pause of an upload.
+    pub grace_period: Duration,
+    /// Only find the garbage and report it, without removing anything. Removing garbage may turn more file items into garbage, such as the references to removed files, which are not reported by a dry run.
+    pub dry_run:      bool,
+}
+
+impl Default for GarbageOptions {
+    #[inline]
+    fn default() -> Self {
+        GarbageOptions {
+            grace_period: DEFAULT_GARBAGE_GRACE_PERIOD, dry_run: false
+        }
+    }
+}
+
+/// The default number of documents scanned in each batch of `FileCenter::clear_garbage_incrementally`.
+pub const DEFAULT_GARBAGE_BATCH_SIZE: usize = 1000;
+/// The default grace period of `FileCenter::clear_garbage` and `FileCenter::clear_garbage_incrementally`.
+pub const DEFAULT_GARBAGE_GRACE_PERIOD: Duration = Duration::from_secs(3600);
+
+/// How `FileCenter::clear_garbage_incrementally` removes the garbage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncrementalGarbageOptions {
+    /// The maximum number of file items or chunks scanned in each batch. `0` is treated as `1`.
+    pub batch_size:   usize,
+    /// The maximum number of batches in this run. If it is `None`, the run continues until the whole file center has been scanned.
+    pub max_batches:  Option<usize>,
+    /// The chunks of a file are not removed until this period has passed since the last one of them was inserted. It has to be longer than the longest pause of an upload.
+    pub grace_period: Duration,
+    /// Only find the garbage and report it, without removing anything or storing the checkpoint.
+    pub dry_run:      bool,
+}
+
+impl Default for IncrementalGarbageOptions {
+    #[inline]
+    fn default() -> Self {
+        IncrementalGarbageOptions {
+            batch_size:   DEFAULT_GARBAGE_BATCH_SIZE,
+            max_batches:  None,
+            grace_period: DEFAULT_GARBAGE_GRACE_PERIOD,
+            dry_run:      false,
+        }
+    }
+}
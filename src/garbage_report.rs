@@ -0,0 +1,62 @@
+use crate::bson::oid::ObjectId;
+
+/// The maximum number of IDs listed in each list of a `GarbageReport`.
+pub const MAX_GARBAGE_REPORT_IDS: usize = 1000;
+
+/// The statistics of `FileCenter::clear_garbage`. In a dry run, they describe the garbage which would be removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GarbageReport {
+    /// Whether nothing has been removed.
+    pub dry_run:                 bool,
+    /// Whether the whole file center has been scanned. It is `false` if `clear_garbage_incrementally` stops because of `max_batches`.
+    pub completed:               bool,
+    /// The number of the file items whose chunks or file contents do not exist.
+    pub orphan_item_count:       u64,
+    /// The IDs of the first `MAX_GARBAGE_REPORT_IDS` file items whose chunks or file contents do not exist.
+    pub orphan_items:            Vec<ObjectId>,
+    /// The number of the file items whose counts are smaller than or equal to 0.
+    pub zero_count_item_count:   u64,
+    /// The IDs of the first `MAX_GARBAGE_REPORT_IDS` file items whose counts are smaller than or equal to 0.
+    pub zero_count_items:        Vec<ObjectId>,
+    /// The number of the files whose chunks are not used by any file item.
+    pub orphan_chunk_file_count: u64,
+    /// The first `MAX_GARBAGE_REPORT_IDS` file IDs of the chunks which are not used by any file item.
+    pub orphan_chunk_file_ids:   Vec<ObjectId>,
+    /// The number of chunks removed, including the chunks of the removed file items.
+    pub removed_chunks:          u64,
+    /// The size of the stored data removed, in bytes.
+    pub reclaimed_bytes:         u64,
+}
+
+impl GarbageReport {
+    /// Whether no garbage has been found.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.orphan_item_count == 0
+            && self.zero_count_item_count == 0
+            && self.orphan_chunk_file_count == 0
+    }
+
+    #[inline]
+    pub(crate) fn add_orphan_items(&mut self, ids: Vec<ObjectId>) {
+        add_ids(&mut self.orphan_item_count, &mut self.orphan_items, ids);
+    }
+
+    #[inline]
+    pub(crate) fn add_zero_count_items(&mut self, ids: Vec<ObjectId>) {
+        add_ids(&mut self.zero_count_item_count, &mut self.zero_count_items, ids);
+    }
+
+    #[inline]
+    pub(crate) fn add_orphan_chunk_file_ids(&mut self, ids: Vec<ObjectId>) {
+        add_ids(&mut self.orphan_chunk_file_count, &mut self.orphan_chunk_file_ids, ids);
+    }
+}
+
+fn add_ids(count: &mut u64, list: &mut Vec<ObjectId>, ids: Vec<ObjectId>) {
+    *count += ids.len() as u64;
+
+    let remaining = MAX_GARBAGE_REPORT_IDS.saturating_sub(list.len());
+
+    list.extend(ids.into_iter().take(remaining));
+}
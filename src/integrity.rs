@@ -0,0 +1,230 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, Cursor, ErrorKind},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use crate::{
+    bson::Document, functions::separate_hash, mongodb, tokio_stream::Stream, Digest,
+    FileDataStream, Hasher,
+};
+
+/// The file data retrieved from MongoDB is broken. It is wrapped in the `io::Error` returned from the stream of the file data, and can be obtained by `io::Error::get_ref` and `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityError {
+    /// The chunk whose index is `n` does not exist.
+    MissingChunk { n: i64 },
+    /// The data of the chunk whose index is `n` does not match its checksum.
+    ChunkChecksumMismatch { n: i64 },
+    /// The size of the data does not match the file size.
+    FileSizeMismatch { expected: u64, actual: u64 },
+    /// The SHA-256 hash of the data does not match the hash of the file.
+    HashMismatch,
+}
+
+impl Display for IntegrityError {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            IntegrityError::MissingChunk {
+                n,
+            } => f.write_fmt(format_args!("the chunk {} is missing", n)),
+            IntegrityError::ChunkChecksumMismatch {
+                n,
+            } => f.write_fmt(format_args!("the checksum of the chunk {} does not match", n)),
+            IntegrityError::FileSizeMismatch {
+                expected,
+                actual,
+            } => f.write_fmt(format_args!(
+                "the file size should be {}, but {} bytes have been read",
+                expected, actual
+            )),
+            IntegrityError::HashMismatch => f.write_str("the hash of the file does not match"),
+        }
+    }
+}
+
+impl Error for IntegrityError {}
+
+impl From<IntegrityError> for io::Error {
+    #[inline]
+    fn from(err: IntegrityError) -> Self {
+        io::Error::new(ErrorKind::InvalidData, err)
+    }
+}
+
+/// Compute the checksum of the data of a chunk.
+#[inline]
+pub(crate) fn chunk_checksum(data: &[u8]) -> i64 {
+    crc32fast::hash(data) as i64
+}
+
+/// To check that the chunks retrieved from MongoDB are contiguous and match their checksums.
+pub(crate) struct ChunkVerifier<S> {
+    stream: S,
+    next_n: i64,
+    last_n: Option<i64>,
+    done:   bool,
+}
+
+impl<S> ChunkVerifier<S> {
+    /// `last_n` is the index of the last chunk, if it is known.
+    #[inline]
+    pub(crate) fn new(stream: S, first_n: i64, last_n: Option<i64>) -> ChunkVerifier<S> {
+        ChunkVerifier {
+            stream,
+            next_n: first_n,
+            last_n,
+            done: false,
+        }
+    }
+
+    fn verify(&mut self, chunk: Document) -> Result<Document, io::Error> {
+        let n = chunk.get_i64("n").map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+        if n != self.next_n {
+            return Err(IntegrityError::MissingChunk {
+                n: self.next_n
+            }
+            .into());
+        }
+
+        // chunks which were stored before checksums were introduced do not have one
+        if let Ok(checksum) = chunk.get_i64("checksum") {
+            let data = chunk
+                .get_binary_generic("data")
+                .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+            if checksum != chunk_checksum(data) {
+                return Err(IntegrityError::ChunkChecksumMismatch {
+                    n,
+                }
+                .into());
+            }
+        }
+
+        self.next_n += 1;
+
+        Ok(chunk)
+    }
+}
+
+impl<S: Stream<Item = mongodb::error::Result<Document>> + Unpin> Stream for ChunkVerifier<S> {
+    type Item = Result<Document, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        let result = match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+            Some(Ok(chunk)) => this.verify(chunk),
+            Some(Err(err)) => Err(io::Error::new(ErrorKind::InvalidData, err)),
+            None => {
+                this.done = true;
+
+                return match this.last_n {
+                    Some(last_n) if this.next_n <= last_n => {
+                        Poll::Ready(Some(Err(IntegrityError::MissingChunk {
+                            n: this.next_n
+                        }
+                        .into())))
+                    },
+                    _ => Poll::Ready(None),
+                };
+            },
+        };
+
+        if result.is_err() {
+            this.done = true;
+        }
+
+        Poll::Ready(Some(result))
+    }
+}
+
+/// To check the size and the hash of the whole data after the stream is read to the end.
+pub(crate) struct HashVerifier {
+    stream:    FileDataStream,
+    file_size: u64,
+    hash:      Option<(Hasher, (i64, i64, i64, i64))>,
+    size:      u64,
+    done:      bool,
+}
+
+impl HashVerifier {
+    #[inline]
+    pub(crate) fn new(
+        stream: FileDataStream,
+        file_size: u64,
+        hash: Option<(i64, i64, i64, i64)>,
+    ) -> HashVerifier {
+        HashVerifier {
+            stream,
+            file_size,
+            hash: hash.map(|hash| (Hasher::new(), hash)),
+            size: 0,
+            done: false,
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), IntegrityError> {
+        if self.size != self.file_size {
+            return Err(IntegrityError::FileSizeMismatch {
+                expected: self.file_size,
+                actual:   self.size,
+            });
+        }
+
+        if let Some((hasher, hash)) = self.hash.take() {
+            if separate_hash(&hasher.finalize()) != hash {
+                return Err(IntegrityError::HashMismatch);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Stream for HashVerifier {
+    type Item = Result<Cursor<Vec<u8>>, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                let data = chunk.get_ref();
+
+                this.size += data.len() as u64;
+
+                if let Some((hasher, _)) = this.hash.as_mut() {
+                    hasher.update(data);
+                }
+
+                Poll::Ready(Some(Ok(chunk)))
+            },
+            Some(Err(err)) => {
+                this.done = true;
+
+                Poll::Ready(Some(Err(err)))
+            },
+            None => {
+                this.done = true;
+
+                match this.finish() {
+                    Ok(()) => Poll::Ready(None),
+                    Err(err) => Poll::Ready(Some(Err(err.into()))),
+                }
+            },
+        }
+    }
+}
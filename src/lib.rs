@@ -3,11 +3,27 @@
 
 This crate aims to build an easy-to-use and no-redundant file storage based on MongoDB.
 
-For perennial files, each of them is unique in the database, and can be retrieved many times without limitation.
+For perennial files, the content of each of them is stored only once in the database, and can be retrieved many times without limitation. Putting the same content again creates a new reference to it, which has its own ID, file name, MIME type and metadata.
 
-For temporary files, they are allowed to be duplicated, but each instance can be retrieved only one time in a minute after it is created.
+For temporary files, they are allowed to be duplicated, but each instance can be retrieved only one time in a minute after it is created. The life time can be changed for the whole file center with `set_temporary_life_time`, or for a single file with `UploadOptions::expiration`.
 
-The file data can be stored in a document or be separated into chunks to store in multiple documents. It depends on the size of data and the `file_size_threshold`. If the size is smaller than or equal to the threshold, it stores in a single document. The max threshold is **16770KB**. The default threshold is **256KiB**.
+The file data can be stored in a document or be separated into chunks to store in multiple documents. It depends on the size of data and the `file_size_threshold`. If the size is smaller than or equal to the threshold, it stores in a single document. The max threshold is **16770KB**. The default threshold is **256KiB**. The size of chunks is set separately by `set_chunk_size`, and its default is also **256KiB**.
+
+The data of textual files can be compressed with gzip or zstd by setting `UploadOptions::compression`. It is decompressed transparently when the file is read, and `FileItem::into_raw_file_data` returns the stored bytes instead, which can be sent with a `Content-Encoding` header as they are. Gzip and zstd are provided by the `gzip` and `zstd` features, which are enabled by default.
+
+Every chunk is stored with a checksum. When a file is read as a stream, missing or corrupted chunks, and a size or SHA-256 hash which does not match the file, are reported as an `IntegrityError` instead of being sent out silently.
+
+`clear_garbage` removes orphaned file items and chunks and reports what it has removed. `clear_garbage_with_options` can run it as a dry run to preview the removal. They scan the file center in bounded batches and keep the chunks of a file while one of them was inserted within a grace period. `clear_garbage_incrementally` also stores a checkpoint, so that a later call can resume from where the previous one stopped. Instead of running these jobs by hand, `spawn_maintenance` spawns a background task which clears the garbage, removes expired temporary files and enforces a retention policy periodically. Only one of the tasks sharing a file center does the work at a time.
+
+`verify` scans the whole file center for missing chunks, size and hash mismatches, negative counts and file items without data, and can optionally repair the counts or remove the broken files.
+
+With `set_encryption`, the data of files is encrypted with AES-256-GCM or ChaCha20-Poly1305 before being written to MongoDB, and decrypted transparently when it is read. Each key has an ID which is stored with the files, so older keys can be kept in the `Encryption` instance for reading after the current key is rotated. The keys are never stored in the database. Every chunk is authenticated along with the ID of its file and its index, so encrypted chunks cannot be moved to another file or reordered without being detected. Files are only deduplicated into content which is encrypted with the same key. The SHA-256 hashes of perennial files are still stored in plain, in order to deduplicate their content, so anyone who can read the database can confirm whether a known file is stored. The ciphers are provided by the `encryption` feature, which is enabled by default.
+
+To share the connection pool, the TLS settings and the monitoring of an application, a file center can be created on an existing client with `new_with_client`, or on an existing database with `new_with_database`. `FileCenterBuilder` creates a file center from a URI, an existing client or an existing database, with custom collection names or a collection prefix (so several file centers can share one database), the initial settings, read and write concerns, and whether to create the indexes on startup. `bucket` opens another file center in the same database with the same client, whose collections and settings, including the key of ID tokens, are separated.
+
+Files can be given an owner with `UploadOptions::owner`. `usage` reports the number and the total size of the files of an owner from a counter which is updated whenever files are put or removed, counting every file with its full size even if its content is deduplicated. `set_quota` limits them, and an upload which would exceed the quota fails with `FileCenterError::QuotaExceeded`, before or while its data is stored.
+
+On a replica set or a sharded cluster, `set_transactions_enabled` makes every upload and deletion run in a transaction, so an interrupted operation never leaves orphaned chunks behind.
 
 ## Example
 
@@ -54,19 +70,51 @@ pub extern crate bson;
 #[macro_use]
 extern crate educe;
 
+mod chunk_batch;
+mod content_encoding;
+mod data_encoder;
+mod download_options;
+mod encryption;
 mod file_center;
+mod file_center_builder;
 mod file_center_error;
 mod file_data;
+mod file_info;
 mod file_item;
+mod file_query;
+mod file_upload_stream;
 mod functions;
-
+mod garbage_options;
+mod garbage_report;
+mod integrity;
+mod maintenance;
+mod quota;
+mod session;
+mod upload_options;
+mod verification;
+
+pub use chunk_batch::*;
+pub use content_encoding::*;
+pub use download_options::*;
+pub use encryption::*;
 pub use file_center::*;
+pub use file_center_builder::*;
 pub use file_center_error::*;
 pub use file_data::*;
+pub use file_info::*;
 pub use file_item::*;
+pub use file_query::*;
+pub use file_upload_stream::*;
+pub use garbage_options::*;
+pub use garbage_report::*;
+pub use integrity::*;
+pub use maintenance::*;
 use mime::{Mime, APPLICATION_OCTET_STREAM};
+pub use quota::*;
 use sha2::{Digest, Sha256 as Hasher};
 pub use tokio_stream::{Stream, StreamExt};
+pub use upload_options::*;
+pub use verification::*;
 
 /// The default mime type.
 pub const DEFAULT_MIME_TYPE: Mime = APPLICATION_OCTET_STREAM;
@@ -0,0 +1,514 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    bson::{oid::ObjectId, DateTime},
+    file_center::{collect_ids, is_duplicate_key_error},
+    mongodb::options::{FindOptions, UpdateOptions},
+    tokio::{
+        self,
+        sync::oneshot::{self, error::TryRecvError},
+        task::{JoinError, JoinHandle},
+        time::{self, Instant},
+    },
+    tokio_stream::StreamExt,
+    FileCenter, FileCenterError, FileQuery, IncrementalGarbageOptions, SETTING_MAINTENANCE_LEASE,
+};
+
+/// The default interval of the garbage collection run by the background maintenance task.
+pub const DEFAULT_MAINTENANCE_GARBAGE_INTERVAL: Duration = Duration::from_secs(3600);
+/// The default interval of removing expired temporary files in the background maintenance task.
+pub const DEFAULT_MAINTENANCE_EXPIRED_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+/// The default interval of enforcing the retention policy in the background maintenance task.
+pub const DEFAULT_MAINTENANCE_RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+/// The default maximum random delay added to every interval of the background maintenance task.
+pub const DEFAULT_MAINTENANCE_JITTER: Duration = Duration::from_secs(30);
+/// The default time the lease of the background maintenance task lasts.
+pub const DEFAULT_MAINTENANCE_LEASE_TIME: Duration = Duration::from_secs(600);
+/// The default maximum number of batches scanned in each garbage collection run by the background maintenance task.
+pub const DEFAULT_MAINTENANCE_GARBAGE_BATCHES: usize = 100;
+/// The default maximum number of files removed in each batch of the background maintenance task.
+pub const DEFAULT_MAINTENANCE_BATCH_SIZE: usize = 1000;
+
+/// What the background maintenance task does, and how often. A job whose interval is `None` is disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceOptions {
+    /// How often `clear_garbage_incrementally` is run.
+    pub garbage_interval:       Option<Duration>,
+    /// The options passed to `clear_garbage_incrementally`. The batches are run one at a time, and between them the lease is renewed and the shutdown signal is checked. If `max_batches` is `None`, every run continues until the whole file center has been scanned.
+    pub garbage_options:        IncrementalGarbageOptions,
+    /// How often the expired temporary files are removed.
+    pub expired_sweep_interval: Option<Duration>,
+    /// Perennial files which were created longer ago than this are removed. If it is `None`, files are kept forever.
+    pub retention:              Option<Duration>,
+    /// How often the retention policy is enforced.
+    pub retention_interval:     Duration,
+    /// The maximum number of files removed in each batch when the expired temporary files are removed or the retention policy is enforced. Between the batches, the lease is renewed and the shutdown signal is checked. `0` is treated as `1`.
+    pub batch_size:             usize,
+    /// A random delay between zero and this is added to every interval, so that the tasks of multiple processes do not start at the same time.
+    pub jitter:                 Duration,
+    /// How long the lease lasts after the task has acquired or renewed it. Among all the tasks running on the same file center, only the one holding the lease does the work. If the task stops without releasing the lease, another one takes over after it expires.
+    pub lease_time:             Duration,
+}
+
+impl Default for MaintenanceOptions {
+    #[inline]
+    fn default() -> Self {
+        MaintenanceOptions {
+            garbage_interval:       Some(DEFAULT_MAINTENANCE_GARBAGE_INTERVAL),
+            garbage_options:        IncrementalGarbageOptions {
+                max_batches: Some(DEFAULT_MAINTENANCE_GARBAGE_BATCHES),
+                ..IncrementalGarbageOptions::default()
+            },
+            expired_sweep_interval: Some(DEFAULT_MAINTENANCE_EXPIRED_SWEEP_INTERVAL),
+            retention:              None,
+            retention_interval:     DEFAULT_MAINTENANCE_RETENTION_INTERVAL,
+            batch_size:             DEFAULT_MAINTENANCE_BATCH_SIZE,
+            jitter:                 DEFAULT_MAINTENANCE_JITTER,
+            lease_time:             DEFAULT_MAINTENANCE_LEASE_TIME,
+        }
+    }
+}
+
+/// The handle of the background maintenance task spawned by `FileCenter::spawn_maintenance`.
+#[derive(Debug)]
+pub struct MaintenanceHandle {
+    shutdown: oneshot::Sender<()>,
+    join:     JoinHandle<()>,
+}
+
+impl MaintenanceHandle {
+    /// Ask the task to stop, and wait for it. The job which is running stops after its current batch, and then the lease is released.
+    pub async fn shutdown(self) -> Result<(), JoinError> {
+        if self.shutdown.send(()).is_err() {
+            // do nothing
+        }
+
+        self.join.await
+    }
+
+    /// Whether the task has stopped.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.join.is_finished()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MaintenanceJob {
+    Garbage,
+    ExpiredSweep,
+    Retention,
+}
+
+/// Get a random duration between zero and `max`.
+fn random_jitter(max: Duration) -> Duration {
+    let max = max.as_millis().min(u64::MAX as u128) as u64;
+
+    if max == 0 {
+        return Duration::ZERO;
+    }
+
+    let random = RandomState::new().build_hasher().finish();
+
+    Duration::from_millis(random % max)
+}
+
+/// Whether the shutdown signal has been received, or the handle has been dropped.
+#[inline]
+fn is_shutdown_requested(shutdown_receiver: &mut oneshot::Receiver<()>) -> bool {
+    !matches!(shutdown_receiver.try_recv(), Err(TryRecvError::Empty))
+}
+
+impl FileCenter {
+    /// Spawn a task on the tokio runtime which periodically clears the garbage, removes expired temporary files, and enforces the retention policy, according to `options`. Errors are ignored, and the job is retried at its next run.
+    ///
+    /// The task can be spawned by multiple processes which share the same file center. A lease stored in the settings collection makes sure that only one of them does the work at a time.
+    pub fn spawn_maintenance(self: Arc<Self>, options: MaintenanceOptions) -> MaintenanceHandle {
+        let (shutdown, mut shutdown_receiver) = oneshot::channel();
+
+        let join = tokio::spawn(async move {
+            let owner = ObjectId::new();
+
+            let mut jobs = Vec::with_capacity(3);
+
+            if let Some(interval) = options.garbage_interval {
+                jobs.push((MaintenanceJob::Garbage, interval));
+            }
+
+            if let Some(interval) = options.expired_sweep_interval {
+                jobs.push((MaintenanceJob::ExpiredSweep, interval));
+            }
+
+            if options.retention.is_some() {
+                jobs.push((MaintenanceJob::Retention, options.retention_interval));
+            }
+
+            let now = Instant::now();
+
+            let mut schedule: Vec<(MaintenanceJob, Duration, Instant)> = jobs
+                .into_iter()
+                .map(|(job, interval)| (job, interval, now + random_jitter(options.jitter)))
+                .collect();
+
+            'schedule: while let Some(next) = schedule.iter().map(|(_, _, next)| *next).min() {
+                tokio::select! {
+                    _ = &mut shutdown_receiver => break,
+                    _ = time::sleep_until(next) => (),
+                }
+
+                let now = Instant::now();
+
+                for (job, interval, next) in schedule.iter_mut() {
+                    if *next > now {
+                        continue;
+                    }
+
+                    if is_shutdown_requested(&mut shutdown_receiver) {
+                        break 'schedule;
+                    }
+
+                    *next = now + *interval + random_jitter(options.jitter);
+
+                    if !matches!(
+                        self.acquire_maintenance_lease(owner, options.lease_time).await,
+                        Ok(true)
+                    ) {
+                        continue;
+                    }
+
+                    let result = self
+                        .run_maintenance_job(*job, owner, &options, &mut shutdown_receiver)
+                        .await;
+
+                    if let Ok(true) = result {
+                        break 'schedule;
+                    }
+                }
+            }
+
+            if self.release_maintenance_lease(owner).await.is_err() {
+                // do nothing
+            }
+        });
+
+        MaintenanceHandle {
+            shutdown,
+            join,
+        }
+    }
+
+    /// Run a job. Returns `true` if the shutdown signal has been received during it.
+    async fn run_maintenance_job(
+        &self,
+        job: MaintenanceJob,
+        owner: ObjectId,
+        options: &MaintenanceOptions,
+        shutdown_receiver: &mut oneshot::Receiver<()>,
+    ) -> Result<bool, FileCenterError> {
+        let batch_size = options.batch_size.max(1);
+
+        match job {
+            MaintenanceJob::Garbage => {
+                self.run_garbage_job(owner, options, shutdown_receiver).await
+            },
+            MaintenanceJob::ExpiredSweep => {
+                let mut batches = 0;
+
+                loop {
+                    if batches > 0 {
+                        if let Some(shutdown) =
+                            self.check_maintenance_job(owner, options, shutdown_receiver).await?
+                        {
+                            return Ok(shutdown);
+                        }
+                    }
+
+                    if self.delete_expired_files_batch(batch_size).await?.is_none() {
+                        return Ok(false);
+                    }
+
+                    batches += 1;
+                }
+            },
+            MaintenanceJob::Retention => {
+                let retention = match options.retention {
+                    Some(retention) => retention.as_millis().min(i64::MAX as u128) as i64,
+                    None => return Ok(false),
+                };
+
+                let time = DateTime::from_millis(
+                    DateTime::now().timestamp_millis().saturating_sub(retention),
+                );
+
+                let mut after = None;
+                let mut batches = 0;
+
+                loop {
+                    if batches > 0 {
+                        if let Some(shutdown) =
+                            self.check_maintenance_job(owner, options, shutdown_receiver).await?
+                        {
+                            return Ok(shutdown);
+                        }
+                    }
+
+                    if self
+                        .delete_files_created_before_batch(time, &mut after, batch_size)
+                        .await?
+                        .is_none()
+                    {
+                        return Ok(false);
+                    }
+
+                    batches += 1;
+                }
+            },
+        }
+    }
+
+    /// Check whether a job can go on with its next batch. Returns `Some(true)` if the shutdown signal has been received, and `Some(false)` if the lease cannot be renewed.
+    async fn check_maintenance_job(
+        &self,
+        owner: ObjectId,
+        options: &MaintenanceOptions,
+        shutdown_receiver: &mut oneshot::Receiver<()>,
+    ) -> Result<Option<bool>, FileCenterError> {
+        if is_shutdown_requested(shutdown_receiver) {
+            return Ok(Some(true));
+        }
+
+        if !self.acquire_maintenance_lease(owner, options.lease_time).await? {
+            return Ok(Some(false));
+        }
+
+        Ok(None)
+    }
+
+    /// Clear the garbage one batch at a time, up to `max_batches` batches. It stops early if the lease cannot be renewed. Returns `true` if the shutdown signal has been received.
+    async fn run_garbage_job(
+        &self,
+        owner: ObjectId,
+        options: &MaintenanceOptions,
+        shutdown_receiver: &mut oneshot::Receiver<()>,
+    ) -> Result<bool, FileCenterError> {
+        let batch_options = IncrementalGarbageOptions {
+            max_batches: Some(1),
+            ..options.garbage_options
+        };
+
+        let mut position = self.load_garbage_checkpoint().await?;
+
+        let mut batches = 0;
+
+        loop {
+            if let Some(max_batches) = options.garbage_options.max_batches {
+                if batches >= max_batches {
+                    return Ok(false);
+                }
+            }
+
+            if batches > 0 {
+                if let Some(shutdown) =
+                    self.check_maintenance_job(owner, options, shutdown_receiver).await?
+                {
+                    return Ok(shutdown);
+                }
+            }
+
+            let report = self.collect_garbage(batch_options, true, &mut position).await?;
+
+            if report.completed {
+                return Ok(false);
+            }
+
+            batches += 1;
+        }
+    }
+
+    /// Acquire the lease, or renew it if `owner` already holds it. Returns `false` if another task holds it.
+    async fn acquire_maintenance_lease(
+        &self,
+        owner: ObjectId,
+        lease_time: Duration,
+    ) -> Result<bool, FileCenterError> {
+        let now = DateTime::now();
+        let lease_time = lease_time.as_millis().min(i64::MAX as u128) as i64;
+
+        let mut options = UpdateOptions::default();
+        options.upsert = Some(true);
+
+        let result = self
+            .collections
+            .settings
+            .update_one(
+                doc! {
+                    "_id": SETTING_MAINTENANCE_LEASE,
+                    "$or": [
+                        {
+                            "value.owner": owner
+                        },
+                        {
+                            "value.expire_at": {
+                                "$lte": now
+                            }
+                        }
+                    ]
+                },
+                doc! {
+                    "$set": {
+                        "value": {
+                            "owner": owner,
+                            "expire_at": DateTime::from_millis(now.timestamp_millis().saturating_add(lease_time))
+                        }
+                    }
+                },
+                Some(options),
+            )
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            // the lease exists and is held by another task
+            Err(err) if is_duplicate_key_error(&err) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn release_maintenance_lease(&self, owner: ObjectId) -> Result<(), FileCenterError> {
+        self.collections
+            .settings
+            .delete_one(
+                doc! {
+                    "_id": SETTING_MAINTENANCE_LEASE,
+                    "value.owner": owner
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remove the temporary file items which have expired. MongoDB removes them automatically as well, but its TTL monitor runs only once a minute. The chunks are kept a little longer, so that the download streams which have been opened can still be finished. Returns the number of the removed file items.
+    pub async fn delete_expired_files(&self) -> Result<u64, FileCenterError> {
+        let mut count = 0;
+
+        while let Some(deleted_count) =
+            self.delete_expired_files_batch(DEFAULT_MAINTENANCE_BATCH_SIZE).await?
+        {
+            count += deleted_count;
+        }
+
+        Ok(count)
+    }
+
+    /// Remove up to `limit` expired temporary file items. Returns `None` if there is no expired file item.
+    async fn delete_expired_files_batch(
+        &self,
+        limit: usize,
+    ) -> Result<Option<u64>, FileCenterError> {
+        let now = DateTime::now();
+
+        let mut options = FindOptions::default();
+        options.limit = Some(limit as i64);
+        options.projection = Some(doc! {
+            "_id": 1
+        });
+
+        let result = self
+            .collections
+            .files
+            .find(
+                doc! {
+                    "expire_at": {
+                        "$lte": now
+                    }
+                },
+                options,
+            )
+            .await?;
+
+        let ids = collect_ids(result, "_id").await?;
+
+        if ids.is_empty() {
+            return Ok(None);
+        }
+
+        let result = self
+            .collections
+            .files
+            .delete_many(
+                doc! {
+                    "_id": {
+                        "$in": ids
+                    },
+                    "expire_at": {
+                        "$lte": now
+                    }
+                },
+                None,
+            )
+            .await?;
+
+        Ok(Some(result.deleted_count))
+    }
+
+    /// Remove the perennial files which were created before `time`, in the same way as `delete_file_item_by_id`. Returns the number of the removed files.
+    pub async fn delete_files_created_before(
+        &self,
+        time: DateTime,
+    ) -> Result<u64, FileCenterError> {
+        let mut count = 0;
+        let mut after = None;
+
+        while let Some(deleted_count) = self
+            .delete_files_created_before_batch(time, &mut after, DEFAULT_MAINTENANCE_BATCH_SIZE)
+            .await?
+        {
+            count += deleted_count;
+        }
+
+        Ok(count)
+    }
+
+    /// Remove up to `limit` perennial files which were created before `time` and whose IDs are greater than `after`, and move `after` to the last one. Returns `None` if there is no such file.
+    async fn delete_files_created_before_batch(
+        &self,
+        time: DateTime,
+        after: &mut Option<ObjectId>,
+        limit: usize,
+    ) -> Result<Option<u64>, FileCenterError> {
+        let ids = self
+            .find_file_infos(FileQuery {
+                created_before: Some(time),
+                temporary: Some(false),
+                after: *after,
+                limit: Some(limit.min(u32::MAX as usize) as u32),
+                ..FileQuery::default()
+            })
+            .await?
+            .map(|file_info| file_info.map(|file_info| file_info.get_file_id()))
+            .collect::<Result<Vec<ObjectId>, _>>()
+            .await?;
+
+        match ids.last() {
+            Some(id) => *after = Some(*id),
+            None => return Ok(None),
+        }
+
+        let mut count = 0;
+
+        for id in ids {
+            if self.delete_file_item_by_id(id).await?.is_some() {
+                count += 1;
+            }
+        }
+
+        Ok(Some(count))
+    }
+}
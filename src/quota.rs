@@ -0,0 +1,341 @@
+use crate::{
+    bson::{Bson, DateTime, Document},
+    file_center::bson_to_u64,
+    session::Session,
+    tokio_stream::StreamExt,
+    FileCenter, FileCenterError,
+};
+
+/// The limits of the files of an owner. A limit which is `None` is not enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quota {
+    /// The maximum total size of the files, in bytes.
+    pub max_bytes: Option<u64>,
+    /// The maximum number of the files.
+    pub max_files: Option<u64>,
+}
+
+/// The files stored by an owner.
+///
+/// Every file is counted with its full `file_size`, even if its content is shared with other files by deduplication, until it is deleted. A temporary file is counted until it is removed or expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Usage {
+    /// The total size of the files, in bytes.
+    pub bytes: u64,
+    /// The number of the files.
+    pub files: u64,
+}
+
+impl Quota {
+    /// Whether one more file whose size is `file_size` can be added to `usage`.
+    #[inline]
+    pub fn allows(&self, usage: Usage, file_size: u64) -> bool {
+        if let Some(max_bytes) = self.max_bytes {
+            if usage.bytes.saturating_add(file_size) > max_bytes {
+                return false;
+            }
+        }
+
+        if let Some(max_files) = self.max_files {
+            if usage.files.saturating_add(1) > max_files {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[inline]
+fn option_u64_to_bson(value: Option<u64>) -> Bson {
+    match value {
+        Some(value) => Bson::Int64(value.min(i64::MAX as u64) as i64),
+        None => Bson::Null,
+    }
+}
+
+#[inline]
+fn quota_exceeded(owner: &str) -> FileCenterError {
+    FileCenterError::QuotaExceeded {
+        owner: owner.to_string()
+    }
+}
+
+/// Get the quota stored in the quota document of an owner, which also holds the usage counter.
+#[inline]
+fn document_to_quota(document: &Document) -> Option<Quota> {
+    if document.contains_key("max_bytes") || document.contains_key("max_files") {
+        Some(Quota {
+            max_bytes: document.get("max_bytes").and_then(bson_to_u64),
+            max_files: document.get("max_files").and_then(bson_to_u64),
+        })
+    } else {
+        None
+    }
+}
+
+/// Get the usage counted in the quota document of an owner. The counter may be negative if it has drifted, which is treated as 0.
+#[inline]
+fn document_to_usage(document: &Document) -> Usage {
+    let get = |key: &str| match document.get(key) {
+        Some(Bson::Int32(v)) => (*v).max(0) as u64,
+        Some(Bson::Int64(v)) => (*v).max(0) as u64,
+        _ => 0,
+    };
+
+    Usage {
+        bytes: get("bytes"), files: get("files")
+    }
+}
+
+/// Get the owner of a perennial file item, and the bytes and the number of files which it adds to the usage counter of the owner. Temporary files are not counted by the counter, because they are removed by the TTL index.
+pub(crate) fn file_item_usage(file_item: &Document) -> Option<(&str, i64, i64)> {
+    let owner = file_item.get_str("owner").ok()?;
+
+    if file_item.contains_key("expire_at") {
+        return None;
+    }
+
+    // a detached file content is kept only for its references
+    if file_item.get_bool("detached").unwrap_or(false) {
+        return None;
+    }
+
+    Some((owner, file_item.get_i64("file_size").unwrap_or(0), 1))
+}
+
+impl FileCenter {
+    /// Get the usage of an owner, which is given by `UploadOptions::owner` when files are put.
+    pub async fn usage<S: AsRef<str>>(&self, owner: S) -> Result<Usage, FileCenterError> {
+        let owner = owner.as_ref();
+
+        let counter = self
+            .collections
+            .quotas
+            .find_one(
+                doc! {
+                    "_id": owner
+                },
+                None,
+            )
+            .await?;
+
+        let usage = counter.as_ref().map(document_to_usage).unwrap_or_default();
+
+        self.add_temporary_usage(owner, usage).await
+    }
+
+    /// Add the temporary files of an owner which have not expired to `usage`.
+    async fn add_temporary_usage(
+        &self,
+        owner: &str,
+        usage: Usage,
+    ) -> Result<Usage, FileCenterError> {
+        let mut result = self
+            .collections
+            .files
+            .aggregate(
+                [
+                    doc! {
+                        "$match": {
+                            "owner": owner,
+                            "expire_at": {
+                                "$gt": DateTime::now()
+                            }
+                        }
+                    },
+                    doc! {
+                        "$group": {
+                            "_id": null,
+                            "bytes": {
+                                "$sum": "$file_size"
+                            },
+                            "files": {
+                                "$sum": 1
+                            }
+                        }
+                    },
+                ],
+                None,
+            )
+            .await?;
+
+        match result.try_next().await? {
+            Some(document) => Ok(Usage {
+                bytes: usage
+                    .bytes
+                    .saturating_add(document.get("bytes").and_then(bson_to_u64).unwrap_or(0)),
+                files: usage
+                    .files
+                    .saturating_add(document.get("files").and_then(bson_to_u64).unwrap_or(0)),
+            }),
+            None => Ok(usage),
+        }
+    }
+
+    /// Get the quota of an owner. Returns `None` if the owner has no quota.
+    pub async fn get_quota<S: AsRef<str>>(
+        &self,
+        owner: S,
+    ) -> Result<Option<Quota>, FileCenterError> {
+        let quota = self
+            .collections
+            .quotas
+            .find_one(
+                doc! {
+                    "_id": owner.as_ref()
+                },
+                None,
+            )
+            .await?;
+
+        Ok(quota.as_ref().and_then(document_to_quota))
+    }
+
+    /// Set the quota of an owner, or remove it if `quota` is `None`. The files which have been stored are not affected.
+    pub async fn set_quota<S: AsRef<str>>(
+        &self,
+        owner: S,
+        quota: Option<Quota>,
+    ) -> Result<(), FileCenterError> {
+        let owner = owner.as_ref();
+
+        // the usage counter is stored in the same document, so it is kept when the quota is removed
+        match quota {
+            Some(quota) => {
+                Session::none()
+                    .upsert_one(
+                        &self.collections.quotas,
+                        doc! {
+                            "_id": owner
+                        },
+                        doc! {
+                            "$set": {
+                                "max_bytes": option_u64_to_bson(quota.max_bytes),
+                                "max_files": option_u64_to_bson(quota.max_files),
+                            }
+                        },
+                    )
+                    .await?;
+            },
+            None => {
+                self.collections
+                    .quotas
+                    .update_one(
+                        doc! {
+                            "_id": owner
+                        },
+                        doc! {
+                            "$unset": {
+                                "max_bytes": "",
+                                "max_files": "",
+                            }
+                        },
+                        None,
+                    )
+                    .await?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Get the number of bytes which an owner can still put, after checking that one more file can be put. Returns `None` if the size is not limited.
+    pub(crate) async fn get_quota_budget(
+        &self,
+        owner: Option<&str>,
+    ) -> Result<Option<u64>, FileCenterError> {
+        let owner = match owner {
+            Some(owner) => owner,
+            None => return Ok(None),
+        };
+
+        let document = self
+            .collections
+            .quotas
+            .find_one(
+                doc! {
+                    "_id": owner
+                },
+                None,
+            )
+            .await?;
+
+        let (quota, usage) = match document.as_ref() {
+            Some(document) => match document_to_quota(document) {
+                Some(quota) => (quota, document_to_usage(document)),
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        let usage = self.add_temporary_usage(owner, usage).await?;
+
+        if !quota.allows(usage, 0) {
+            return Err(quota_exceeded(owner));
+        }
+
+        Ok(quota.max_bytes.map(|max_bytes| max_bytes.saturating_sub(usage.bytes)))
+    }
+
+    /// Check whether a file whose size is `file_size` can be put for an owner. Concurrent uploads of the same owner are checked independently, so they may exceed the quota slightly.
+    pub(crate) async fn check_quota(
+        &self,
+        owner: Option<&str>,
+        file_size: u64,
+    ) -> Result<(), FileCenterError> {
+        match self.get_quota_budget(owner).await? {
+            Some(budget) if file_size > budget => Err(quota_exceeded(owner.unwrap_or_default())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Add `bytes` and `files` to the usage counter of an owner.
+    pub(crate) async fn add_usage(
+        &self,
+        owner: Option<&str>,
+        bytes: i64,
+        files: i64,
+        session: &mut Session,
+    ) -> Result<(), FileCenterError> {
+        let owner = match owner {
+            Some(owner) => owner,
+            None => return Ok(()),
+        };
+
+        if bytes == 0 && files == 0 {
+            return Ok(());
+        }
+
+        session
+            .upsert_one(
+                &self.collections.quotas,
+                doc! {
+                    "_id": owner
+                },
+                doc! {
+                    "$inc": {
+                        "bytes": bytes,
+                        "files": files,
+                    }
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remove what a file item which is being removed adds to the usage counter of its owner.
+    pub(crate) async fn remove_file_item_usage(
+        &self,
+        file_item: &Document,
+        session: &mut Session,
+    ) -> Result<(), FileCenterError> {
+        match file_item_usage(file_item) {
+            Some((owner, bytes, files)) => {
+                self.add_usage(Some(owner), -bytes, -files, session).await
+            },
+            None => Ok(()),
+        }
+    }
+}
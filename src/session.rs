@@ -0,0 +1,251 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    bson::Document,
+    mongodb::{
+        error::{Result, TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT},
+        options::{FindOneAndDeleteOptions, FindOneAndUpdateOptions, UpdateOptions},
+        results::{DeleteResult, InsertManyResult, InsertOneResult, UpdateResult},
+        Client, ClientSession, Collection,
+    },
+    FileCenterError,
+};
+
+/// How long a transaction is retried after it fails with a transient error. It is the same as the limit of the `with_transaction` helpers of the MongoDB drivers.
+const TRANSACTION_RETRY_TIME_LIMIT: Duration = Duration::from_secs(120);
+
+/// Whether the transaction in which `err` occurred can be run again from the beginning.
+#[inline]
+fn is_transient_transaction_error(err: &FileCenterError) -> bool {
+    matches!(err, FileCenterError::MongoDBError(err) if err.contains_label(TRANSIENT_TRANSACTION_ERROR))
+}
+
+/// A MongoDB session which runs a transaction if transactions are enabled. Otherwise, every operation is executed on its own.
+pub(crate) struct Session {
+    session:    Option<ClientSession>,
+    start_time: Instant,
+}
+
+impl Session {
+    /// Create a session which does not run a transaction.
+    #[inline]
+    pub(crate) fn none() -> Session {
+        Session {
+            session: None, start_time: Instant::now()
+        }
+    }
+
+    /// Start a transaction if `transactions` is `true`.
+    pub(crate) async fn start(client: &Client, transactions: bool) -> Result<Session> {
+        if transactions {
+            let mut session = client.start_session(None).await?;
+
+            session.start_transaction(None).await?;
+
+            Ok(Session {
+                session: Some(session), start_time: Instant::now()
+            })
+        } else {
+            Ok(Session::none())
+        }
+    }
+
+    /// Commit the transaction if `result` is `Ok`, or abort it. The operation is not run again if the transaction fails with a transient error, which is used for the operations whose data cannot be read twice.
+    #[inline]
+    pub(crate) async fn end<T>(
+        mut self,
+        result: std::result::Result<T, FileCenterError>,
+    ) -> std::result::Result<T, FileCenterError> {
+        self.commit_or_abort(result).await
+    }
+
+    /// Commit the transaction if `result` is `Ok`, or abort it. If the transaction fails with a transient error, such as a write conflict with a concurrent transaction, a new transaction is started and `None` is returned, so that the operation can be run again in it.
+    pub(crate) async fn end_or_restart<T>(
+        &mut self,
+        result: std::result::Result<T, FileCenterError>,
+    ) -> Option<std::result::Result<T, FileCenterError>> {
+        let result = self.commit_or_abort(result).await;
+
+        let session = match self.session.as_mut() {
+            Some(session) => session,
+            None => return Some(result),
+        };
+
+        match result {
+            Err(err)
+                if is_transient_transaction_error(&err)
+                    && self.start_time.elapsed() < TRANSACTION_RETRY_TIME_LIMIT =>
+            {
+                match session.start_transaction(None).await {
+                    Ok(()) => None,
+                    Err(err) => Some(Err(err.into())),
+                }
+            },
+            result => Some(result),
+        }
+    }
+
+    async fn commit_or_abort<T>(
+        &mut self,
+        result: std::result::Result<T, FileCenterError>,
+    ) -> std::result::Result<T, FileCenterError> {
+        match self.session.as_mut() {
+            Some(session) => match result {
+                Ok(value) => {
+                    loop {
+                        match session.commit_transaction().await {
+                            Ok(()) => break,
+                            // the commit may have failed before it reached the server, and committing again is safe
+                            Err(err)
+                                if err.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT)
+                                    && self.start_time.elapsed() < TRANSACTION_RETRY_TIME_LIMIT =>
+                            {
+                                continue
+                            },
+                            Err(err) => return Err(err.into()),
+                        }
+                    }
+
+                    Ok(value)
+                },
+                Err(err) => {
+                    if session.abort_transaction().await.is_err() {
+                        // do nothing
+                    }
+
+                    Err(err)
+                },
+            },
+            None => result,
+        }
+    }
+
+    /// Abort the transaction.
+    pub(crate) async fn abort(self) {
+        if let Some(mut session) = self.session {
+            if session.abort_transaction().await.is_err() {
+                // do nothing
+            }
+        }
+    }
+
+    pub(crate) async fn insert_one(
+        &mut self,
+        collection: &Collection<Document>,
+        document: Document,
+    ) -> Result<InsertOneResult> {
+        match self.session.as_mut() {
+            Some(session) => collection.insert_one_with_session(document, None, session).await,
+            None => collection.insert_one(document, None).await,
+        }
+    }
+
+    pub(crate) async fn insert_many(
+        &mut self,
+        collection: &Collection<Document>,
+        documents: Vec<Document>,
+    ) -> Result<InsertManyResult> {
+        match self.session.as_mut() {
+            Some(session) => collection.insert_many_with_session(documents, None, session).await,
+            None => collection.insert_many(documents, None).await,
+        }
+    }
+
+    pub(crate) async fn update_one(
+        &mut self,
+        collection: &Collection<Document>,
+        filter: Document,
+        update: Document,
+    ) -> Result<UpdateResult> {
+        match self.session.as_mut() {
+            Some(session) => {
+                collection.update_one_with_session(filter, update, None, session).await
+            },
+            None => collection.update_one(filter, update, None).await,
+        }
+    }
+
+    pub(crate) async fn upsert_one(
+        &mut self,
+        collection: &Collection<Document>,
+        filter: Document,
+        update: Document,
+    ) -> Result<UpdateResult> {
+        let mut options = UpdateOptions::default();
+        options.upsert = Some(true);
+
+        match self.session.as_mut() {
+            Some(session) => {
+                collection.update_one_with_session(filter, update, options, session).await
+            },
+            None => collection.update_one(filter, update, options).await,
+        }
+    }
+
+    pub(crate) async fn update_many(
+        &mut self,
+        collection: &Collection<Document>,
+        filter: Document,
+        update: Document,
+    ) -> Result<UpdateResult> {
+        match self.session.as_mut() {
+            Some(session) => {
+                collection.update_many_with_session(filter, update, None, session).await
+            },
+            None => collection.update_many(filter, update, None).await,
+        }
+    }
+
+    pub(crate) async fn delete_one(
+        &mut self,
+        collection: &Collection<Document>,
+        filter: Document,
+    ) -> Result<DeleteResult> {
+        match self.session.as_mut() {
+            Some(session) => collection.delete_one_with_session(filter, None, session).await,
+            None => collection.delete_one(filter, None).await,
+        }
+    }
+
+    pub(crate) async fn delete_many(
+        &mut self,
+        collection: &Collection<Document>,
+        filter: Document,
+    ) -> Result<DeleteResult> {
+        match self.session.as_mut() {
+            Some(session) => collection.delete_many_with_session(filter, None, session).await,
+            None => collection.delete_many(filter, None).await,
+        }
+    }
+
+    pub(crate) async fn find_one_and_update(
+        &mut self,
+        collection: &Collection<Document>,
+        filter: Document,
+        update: Document,
+        options: FindOneAndUpdateOptions,
+    ) -> Result<Option<Document>> {
+        match self.session.as_mut() {
+            Some(session) => {
+                collection
+                    .find_one_and_update_with_session(filter, update, Some(options), session)
+                    .await
+            },
+            None => collection.find_one_and_update(filter, update, Some(options)).await,
+        }
+    }
+
+    pub(crate) async fn find_one_and_delete(
+        &mut self,
+        collection: &Collection<Document>,
+        filter: Document,
+        options: FindOneAndDeleteOptions,
+    ) -> Result<Option<Document>> {
+        match self.session.as_mut() {
+            Some(session) => {
+                collection.find_one_and_delete_with_session(filter, Some(options), session).await
+            },
+            None => collection.find_one_and_delete(filter, Some(options)).await,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use crate::{
+    bson::{DateTime, Document},
+    mime::Mime,
+    Compression, ContentEncoding,
+};
+
+/// When a temporary file expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiration {
+    /// The file expires after the duration since it is created. The duration must be at least one millisecond.
+    After(Duration),
+    /// The file expires at the time instant, which must be later than the time the file is put.
+    At(DateTime),
+}
+
+/// How many times a temporary file can be retrieved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadLimit {
+    /// The file is removed after it has been retrieved this many times. `0` is treated as `1`.
+    Limited(u32),
+    /// The file can be retrieved any number of times until it expires.
+    Unlimited,
+}
+
+/// Extra options used when a file is being put into the file center.
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    /// User-defined metadata which is stored along with the file. If the data of the file does not fit in the file item along with the metadata, it is separated into chunks. Putting fails with `FileCenterError::MetadataTooLargeError` if the metadata and the file name alone are bigger than `MAX_FILE_SIZE_THRESHOLD`.
+    pub metadata:    Option<Document>,
+    /// When a temporary file expires. If it is `None`, the default temporary life time of the file center is used. It is ignored for perennial files. A temporary file which would have expired when it is put is rejected with `FileCenterError::TemporaryLifeTimeError`.
+    pub expiration:  Option<Expiration>,
+    /// How many times a temporary file can be retrieved. If it is `None`, the file can be retrieved only once. It is ignored for perennial files.
+    pub read_limit:  Option<ReadLimit>,
+    /// Whether the data is compressed. If it is `None`, the data is stored as it is.
+    pub compression: Option<Compression>,
+    /// The owner (or tenant) of the file. The file is counted in the usage of the owner, and cannot be put if it would exceed the quota of the owner.
+    pub owner:       Option<String>,
+}
+
+impl UploadOptions {
+    /// Get the encoding which the data of a file which has the MIME type should be compressed with.
+    #[inline]
+    pub(crate) fn get_content_encoding(&self, mime_type: &Mime) -> Option<ContentEncoding> {
+        self.compression.and_then(|compression| compression.get_content_encoding(mime_type))
+    }
+}
@@ -0,0 +1,496 @@
+use crate::{
+    bson::{oid::ObjectId, Bson, Document},
+    encryption::{chunk_associated_data, file_data_associated_data},
+    file_center::get_content_encoding,
+    functions::separate_hash,
+    integrity::chunk_checksum,
+    mongodb::options::{FindOneOptions, FindOptions},
+    session::Session,
+    tokio_stream::StreamExt,
+    ContentEncoding, Digest, EncryptionKey, FileCenter, FileCenterError, Hasher,
+};
+
+/// How `FileCenter::verify` checks the file center and what it repairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyOptions {
+    /// Whether to compute the SHA-256 hash of every perennial file and compare it with the stored one. It reads all the data in the file center.
+    pub check_hashes:        bool,
+    /// Whether to recompute negative `count` values from the references of the files.
+    pub repair_counts:       bool,
+    /// Whether to remove the files whose data is missing or broken, along with their chunks. Files which are still referred to by other files are only reported, so that the references do not become dangling. Files which cannot be checked because their keys are not set are never removed.
+    pub remove_broken_items: bool,
+}
+
+impl Default for VerifyOptions {
+    #[inline]
+    fn default() -> Self {
+        VerifyOptions {
+            check_hashes:        true,
+            repair_counts:       false,
+            remove_broken_items: false,
+        }
+    }
+}
+
+/// A problem found by `FileCenter::verify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationIssue {
+    /// The chunks whose indexes are `missing` do not exist.
+    ChunkGap { file_id: ObjectId, missing: Vec<i64> },
+    /// The data of the chunk whose index is `n` does not match its checksum.
+    ChunkChecksumMismatch { file_id: ObjectId, n: i64 },
+    /// The stored data cannot be decrypted or decompressed.
+    CorruptedData { file_id: ObjectId },
+    /// The size of the data does not match `file_size`.
+    FileSizeMismatch { file_id: ObjectId, expected: u64, actual: u64 },
+    /// The SHA-256 hash of the data does not match the stored hash.
+    HashMismatch { file_id: ObjectId },
+    /// The `count` value is negative.
+    NegativeCount { file_id: ObjectId, count: i32 },
+    /// The file item has neither `file_data` nor `chunk_id`, and is not a reference.
+    MissingData { file_id: ObjectId },
+    /// A field of the file item or of one of its chunks is missing or has an unexpected type.
+    MalformedDocument { file_id: ObjectId },
+    /// The data is encrypted with the key whose ID is `key_id`, which is not set by `set_encryption`, so it cannot be checked.
+    EncryptionKeyNotFound { file_id: ObjectId, key_id: String },
+}
+
+impl VerificationIssue {
+    /// Get the ID of the file item which has the problem.
+    #[inline]
+    pub fn get_file_id(&self) -> ObjectId {
+        match self {
+            VerificationIssue::ChunkGap {
+                file_id, ..
+            }
+            | VerificationIssue::ChunkChecksumMismatch {
+                file_id, ..
+            }
+            | VerificationIssue::CorruptedData {
+                file_id,
+            }
+            | VerificationIssue::FileSizeMismatch {
+                file_id, ..
+            }
+            | VerificationIssue::HashMismatch {
+                file_id,
+            }
+            | VerificationIssue::NegativeCount {
+                file_id, ..
+            }
+            | VerificationIssue::MissingData {
+                file_id,
+            }
+            | VerificationIssue::MalformedDocument {
+                file_id,
+            }
+            | VerificationIssue::EncryptionKeyNotFound {
+                file_id, ..
+            } => *file_id,
+        }
+    }
+}
+
+/// The result of `FileCenter::verify`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// The number of file items which have been checked.
+    pub checked_items:  u64,
+    /// The problems which have been found, including the repaired ones.
+    pub issues:         Vec<VerificationIssue>,
+    /// The number of file items whose count has been recomputed.
+    pub repaired_items: u64,
+    /// The number of file items which have been removed.
+    pub removed_items:  u64,
+}
+
+impl VerificationReport {
+    /// Whether no problem has been found.
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// To decrypt and decompress the stored data of a file item.
+struct DataDecoder {
+    decryption_key:   Option<EncryptionKey>,
+    content_encoding: Option<ContentEncoding>,
+}
+
+impl DataDecoder {
+    fn decode(&self, data: &[u8], associated_data: &[u8]) -> Option<Vec<u8>> {
+        let data = match self.decryption_key.as_ref() {
+            Some(decryption_key) => decryption_key.decrypt(data, associated_data).ok()?,
+            None => data.to_vec(),
+        };
+
+        match self.content_encoding {
+            Some(content_encoding) => content_encoding.decode(&data).ok(),
+            None => Some(data),
+        }
+    }
+}
+
+fn get_hash(document: &Document) -> Option<(i64, i64, i64, i64)> {
+    match (
+        document.get_i64("hash_1"),
+        document.get_i64("hash_2"),
+        document.get_i64("hash_3"),
+        document.get_i64("hash_4"),
+    ) {
+        (Ok(hash_1), Ok(hash_2), Ok(hash_3), Ok(hash_4)) => Some((hash_1, hash_2, hash_3, hash_4)),
+        _ => None,
+    }
+}
+
+impl FileCenter {
+    /// Check the consistency of all the file items and chunks in this file center, and repair the problems if it is enabled in `options`. The keys of encrypted files have to be set by `set_encryption` before checking them.
+    pub async fn verify(
+        &self,
+        options: VerifyOptions,
+    ) -> Result<VerificationReport, FileCenterError> {
+        let mut report = VerificationReport::default();
+
+        let mut find_options = FindOptions::default();
+        find_options.projection = Some(doc! {
+            "_id": 1,
+            "file_size": 1,
+            "file_data": 1,
+            "chunk_id": 1,
+            "chunk_size": 1,
+            "content_id": 1,
+            "count": 1,
+            "detached": 1,
+            "hash_1": 1,
+            "hash_2": 1,
+            "hash_3": 1,
+            "hash_4": 1,
+            "content_encoding": 1,
+            "encryption_key_id": 1,
+            "expire_at": 1,
+            "owner": 1,
+        });
+
+        let mut cursor = self.collections.files.find(None, find_options).await?;
+
+        while let Some(document) = cursor.try_next().await? {
+            let file_id = document.get_object_id("_id")?;
+
+            report.checked_items += 1;
+
+            let mut issues = Vec::new();
+
+            if let Some(count) = document.get("count").and_then(|count| count.as_i32()) {
+                if count < 0 {
+                    issues.push(VerificationIssue::NegativeCount {
+                        file_id,
+                        count,
+                    });
+                }
+            }
+
+            if document.get("content_id").is_none() {
+                self.verify_data(&document, file_id, options.check_hashes, &mut issues).await?;
+            }
+
+            let negative_count =
+                issues.iter().any(|issue| matches!(issue, VerificationIssue::NegativeCount { .. }));
+            let broken = issues.iter().any(|issue| {
+                !matches!(
+                    issue,
+                    VerificationIssue::NegativeCount { .. }
+                        | VerificationIssue::EncryptionKeyNotFound { .. }
+                )
+            });
+
+            if broken && options.remove_broken_items && !self.is_referred_to(file_id).await? {
+                self.remove_file_item_usage(&document, &mut Session::none()).await?;
+
+                self.collections
+                    .files
+                    .delete_one(
+                        doc! {
+                            "_id": file_id
+                        },
+                        None,
+                    )
+                    .await?;
+
+                self.delete_file_chunks(file_id, &mut Session::none()).await?;
+
+                report.removed_items += 1;
+            } else if negative_count && options.repair_counts {
+                self.repair_count(file_id, document.get_bool("detached").unwrap_or(false)).await?;
+
+                report.repaired_items += 1;
+            }
+
+            report.issues.extend(issues);
+        }
+
+        Ok(report)
+    }
+
+    async fn verify_data(
+        &self,
+        document: &Document,
+        file_id: ObjectId,
+        check_hashes: bool,
+        issues: &mut Vec<VerificationIssue>,
+    ) -> Result<(), FileCenterError> {
+        let file_size = match document.get_i64("file_size") {
+            Ok(file_size) => file_size as u64,
+            Err(_) => {
+                issues.push(VerificationIssue::MalformedDocument {
+                    file_id,
+                });
+
+                return Ok(());
+            },
+        };
+
+        let decryption_key = match self.get_decryption_key(document) {
+            Ok(decryption_key) => decryption_key,
+            Err(FileCenterError::EncryptionKeyNotFoundError(key_id)) => {
+                issues.push(VerificationIssue::EncryptionKeyNotFound {
+                    file_id,
+                    key_id,
+                });
+
+                return Ok(());
+            },
+            Err(_) => {
+                issues.push(VerificationIssue::MalformedDocument {
+                    file_id,
+                });
+
+                return Ok(());
+            },
+        };
+
+        let content_encoding = match get_content_encoding(document) {
+            Ok(content_encoding) => content_encoding,
+            Err(_) => {
+                issues.push(VerificationIssue::MalformedDocument {
+                    file_id,
+                });
+
+                return Ok(());
+            },
+        };
+
+        let decoder = DataDecoder {
+            decryption_key,
+            content_encoding,
+        };
+
+        let hash = if check_hashes { get_hash(document) } else { None };
+
+        let mut hasher = Hasher::new();
+
+        let size = match document.get("file_data") {
+            Some(Bson::Binary(b)) => {
+                match decoder.decode(&b.bytes, &file_data_associated_data(file_id)) {
+                    Some(data) => {
+                        if hash.is_some() {
+                            hasher.update(&data);
+                        }
+
+                        data.len() as u64
+                    },
+                    None => {
+                        issues.push(VerificationIssue::CorruptedData {
+                            file_id,
+                        });
+
+                        return Ok(());
+                    },
+                }
+            },
+            Some(_) => {
+                issues.push(VerificationIssue::CorruptedData {
+                    file_id,
+                });
+
+                return Ok(());
+            },
+            None => {
+                if document.get("chunk_id").is_none() {
+                    issues.push(VerificationIssue::MissingData {
+                        file_id,
+                    });
+
+                    return Ok(());
+                }
+
+                let mut find_options = FindOptions::default();
+                find_options.sort = Some(doc! {
+                    "n": 1
+                });
+
+                let mut cursor = self
+                    .collections
+                    .files_chunks
+                    .find(
+                        doc! {
+                            "file_id": file_id
+                        },
+                        find_options,
+                    )
+                    .await?;
+
+                let mut missing = Vec::new();
+                let mut next_n = 0;
+                let mut size = 0;
+                let mut readable = true;
+
+                while let Some(chunk) = cursor.try_next().await? {
+                    let (n, data) = match (chunk.get_i64("n"), chunk.get_binary_generic("data")) {
+                        (Ok(n), Ok(data)) => (n, data),
+                        _ => {
+                            if readable {
+                                issues.push(VerificationIssue::MalformedDocument {
+                                    file_id,
+                                });
+                            }
+
+                            readable = false;
+
+                            continue;
+                        },
+                    };
+
+                    missing.extend(next_n..n);
+                    next_n = n + 1;
+
+                    if let Ok(checksum) = chunk.get_i64("checksum") {
+                        if checksum != chunk_checksum(data) {
+                            issues.push(VerificationIssue::ChunkChecksumMismatch {
+                                file_id,
+                                n,
+                            });
+
+                            readable = false;
+                        }
+                    }
+
+                    if !readable {
+                        continue;
+                    }
+
+                    match decoder.decode(data, &chunk_associated_data(file_id, n)) {
+                        Some(data) => {
+                            if hash.is_some() {
+                                hasher.update(&data);
+                            }
+
+                            size += data.len() as u64;
+                        },
+                        None => {
+                            issues.push(VerificationIssue::CorruptedData {
+                                file_id,
+                            });
+
+                            readable = false;
+                        },
+                    }
+                }
+
+                // the chunks at the end are missing
+                if let Some(chunk_size) = document.get("chunk_size").and_then(|c| c.as_i32()) {
+                    if chunk_size > 0 {
+                        let last_n = (file_size.max(1) - 1) as i64 / chunk_size as i64;
+
+                        missing.extend(next_n..=last_n);
+                    }
+                }
+
+                if !missing.is_empty() {
+                    issues.push(VerificationIssue::ChunkGap {
+                        file_id,
+                        missing,
+                    });
+
+                    readable = false;
+                }
+
+                if !readable {
+                    return Ok(());
+                }
+
+                size
+            },
+        };
+
+        if size != file_size {
+            issues.push(VerificationIssue::FileSizeMismatch {
+                file_id,
+                expected: file_size,
+                actual: size,
+            });
+        } else if let Some(hash) = hash {
+            if separate_hash(&hasher.finalize()) != hash {
+                issues.push(VerificationIssue::HashMismatch {
+                    file_id,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check whether any reference refers to a file item.
+    async fn is_referred_to(&self, file_id: ObjectId) -> Result<bool, FileCenterError> {
+        let mut options = FindOneOptions::default();
+        options.projection = Some(doc! {
+            "_id": 1
+        });
+
+        let reference = self
+            .collections
+            .files
+            .find_one(
+                doc! {
+                    "content_id": file_id
+                },
+                options,
+            )
+            .await?;
+
+        Ok(reference.is_some())
+    }
+
+    /// Recompute the count of a file item, which is the number of its references, plus one if it is not detached.
+    async fn repair_count(&self, file_id: ObjectId, detached: bool) -> Result<(), FileCenterError> {
+        let references = self
+            .collections
+            .files
+            .count_documents(
+                doc! {
+                    "content_id": file_id
+                },
+                None,
+            )
+            .await?;
+
+        let count = references as i32 + if detached { 0 } else { 1 };
+
+        self.collections
+            .files
+            .update_one(
+                doc! {
+                    "_id": file_id
+                },
+                doc! {
+                    "$set": {
+                        "count": count
+                    }
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+}
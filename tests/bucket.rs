@@ -0,0 +1,76 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{
+    mongodb::Client, FileCenter, FileCenterBuilder, FileCenterError, COLLECTION_FILES_CHUNKS_NAME,
+};
+
+#[tokio::test]
+async fn bucket() {
+    let uri = get_mongodb_uri("test_bucket");
+
+    let file_center = FileCenter::new(&uri).await.unwrap();
+
+    let mut avatars = file_center.bucket("avatars").await.unwrap();
+    let attachments = file_center.bucket("attachments").await.unwrap();
+
+    avatars.set_file_size_threshold(65536).await.unwrap();
+
+    // the settings are separated
+    assert_eq!(65536, avatars.get_file_size_threshold());
+    assert_ne!(65536, attachments.get_file_size_threshold());
+    assert_eq!(65536, file_center.bucket("avatars").await.unwrap().get_file_size_threshold());
+
+    let file_id_1 = avatars.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+    let file_id_2 =
+        attachments.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+
+    // the same content is not shared between buckets
+    assert_ne!(file_id_1, file_id_2);
+
+    assert!(avatars.check_file_item_exist(file_id_1).await.unwrap());
+    assert!(!avatars.check_file_item_exist(file_id_2).await.unwrap());
+    assert!(!attachments.check_file_item_exist(file_id_1).await.unwrap());
+    assert!(!file_center.check_file_item_exist(file_id_1).await.unwrap());
+
+    // the ID tokens are encrypted with different keys
+    let id_token = avatars.encrypt_id(file_id_1);
+
+    assert_eq!(file_id_1, avatars.decrypt_id_token(&id_token).unwrap());
+    assert_ne!(id_token, attachments.encrypt_id(file_id_1));
+
+    // the name is validated
+    assert!(matches!(file_center.bucket("").await, Err(FileCenterError::BucketNameError)));
+    assert!(matches!(file_center.bucket("a$b").await, Err(FileCenterError::BucketNameError)));
+
+    // the bucket names are built on the prefix and the custom collection names
+    let prefixed = FileCenterBuilder::from_uri(&uri)
+        .collection_prefix("app_")
+        .files_collection_name("files")
+        .build()
+        .await
+        .unwrap();
+
+    let logos = prefixed.bucket("logos").await.unwrap();
+
+    logos.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+
+    let collection_names = Client::with_uri_str(&uri)
+        .await
+        .unwrap()
+        .database("test_bucket")
+        .list_collection_names(None)
+        .await
+        .unwrap();
+
+    assert!(collection_names.contains(&"app_logos_files".to_string()));
+    assert!(collection_names.contains(&format!("app_logos_{}", COLLECTION_FILES_CHUNKS_NAME)));
+
+    logos.drop_file_center().await.unwrap();
+    prefixed.drop_file_center().await.unwrap();
+
+    avatars.drop_file_center().await.unwrap();
+    attachments.drop_file_center().await.unwrap();
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{
+    bson::{doc, Document},
+    mongodb::{
+        options::{IndexOptions, WriteConcern},
+        Client, IndexModel,
+    },
+    FileCenter, FileCenterBuilder, COLLECTION_FILES_NAME, COLLECTION_SETTINGS_NAME,
+    SETTING_VERSION,
+};
+
+#[tokio::test]
+async fn builder() {
+    let uri = get_mongodb_uri("test_builder");
+
+    let client = Client::with_uri_str(&uri).await.unwrap();
+
+    let file_center_1 = FileCenterBuilder::from_client(client.clone())
+        .database_name("test_builder")
+        .collection_prefix("images_")
+        .file_size_threshold(65536)
+        .write_concern(WriteConcern::builder().journal(true).build())
+        .build()
+        .await
+        .unwrap();
+
+    let file_center_2 = FileCenterBuilder::from_database(client.database("test_builder"))
+        .files_collection_name("documents")
+        .files_chunks_collection_name("documents_chunks")
+        .settings_collection_name("documents_settings")
+        .create_indexes(false)
+        .build()
+        .await
+        .unwrap();
+
+    assert_eq!(65536, file_center_1.get_file_size_threshold());
+
+    let db = client.database("test_builder");
+
+    let collection_names = db.list_collection_names(None).await.unwrap();
+
+    for name in [
+        "images_file_center",
+        "images_file_center_chunks",
+        "images_file_center_settings",
+        "documents_settings",
+    ] {
+        assert!(collection_names.iter().any(|n| n == name), "{name} should exist");
+    }
+
+    // only the `_id` index exists
+    let indexes = db.collection::<Document>("documents").list_index_names().await;
+
+    assert!(indexes.map(|indexes| indexes.len() <= 1).unwrap_or(true));
+
+    // the file centers are separated
+    let file_id_1 =
+        file_center_1.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+    let file_id_2 =
+        file_center_2.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+    assert!(file_center_1.check_file_item_exist(file_id_1).await.unwrap());
+    assert!(!file_center_1.check_file_item_exist(file_id_2).await.unwrap());
+    assert!(file_center_2.check_file_item_exist(file_id_2).await.unwrap());
+    assert!(!file_center_2.check_file_item_exist(file_id_1).await.unwrap());
+
+    assert!(db
+        .collection::<Document>("images_file_center_chunks")
+        .find_one(
+            doc! {
+                "file_id": file_id_1
+            },
+            None
+        )
+        .await
+        .unwrap()
+        .is_some());
+
+    // the garbage collection looks up the collections of its own file center
+    assert!(file_center_1.clear_garbage().await.unwrap().is_empty());
+    assert!(file_center_2.clear_garbage().await.unwrap().is_empty());
+
+    assert!(file_center_1.check_file_item_exist(file_id_1).await.unwrap());
+    assert!(file_center_2.check_file_item_exist(file_id_2).await.unwrap());
+
+    file_center_2.drop_file_center().await.unwrap();
+
+    file_center_1.drop_database().await.unwrap();
+}
+
+#[tokio::test]
+async fn upgrade() {
+    let uri = get_mongodb_uri("test_upgrade");
+
+    let file_center = FileCenter::new(&uri).await.unwrap();
+
+    let db = unsafe { file_center.database() };
+
+    let collection_files = db.collection::<Document>(COLLECTION_FILES_NAME);
+    let collection_settings = db.collection::<Document>(COLLECTION_SETTINGS_NAME);
+
+    // turn the database into one which was created by version 2
+    let legacy_hash_index = {
+        let mut options = IndexOptions::default();
+        options.unique = Some(true);
+
+        let mut index = IndexModel::default();
+
+        index.keys = doc! {
+            "hash_1": 1,
+            "hash_2": 1,
+            "hash_3": 1,
+            "hash_4": 1
+        };
+
+        index.options = Some(options);
+
+        index
+    };
+
+    collection_files.create_index(legacy_hash_index, None).await.unwrap();
+
+    collection_settings
+        .update_one(
+            doc! {
+                "_id": SETTING_VERSION
+            },
+            doc! {
+                "$set": {
+                    "value": 2
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let file_center = FileCenter::new(&uri).await.unwrap();
+
+    let indexes = collection_files.list_index_names().await.unwrap();
+
+    assert!(!indexes.iter().any(|name| name == "hash_1_1_hash_2_1_hash_3_1_hash_4_1"));
+
+    let version = collection_settings
+        .find_one(
+            doc! {
+                "_id": SETTING_VERSION
+            },
+            None,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(3, version.get_i32("value").unwrap());
+
+    // the same content can be deduplicated after the upgrade
+    let file_id_1 =
+        file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+    let file_id_2 =
+        file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+
+    assert!(file_center.check_file_item_exist(file_id_1).await.unwrap());
+    assert!(file_center.check_file_item_exist(file_id_2).await.unwrap());
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{ChunkBatchOptions, FileCenter};
+use tokio::fs::{self, File};
+
+#[tokio::test]
+async fn chunk_batch() {
+    let uri = get_mongodb_uri("test_chunk_batch");
+
+    let mut file_center = FileCenter::new_with_file_size_threshold(uri, 65536).await.unwrap();
+
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    for options in [
+        ChunkBatchOptions {
+            max_chunks: 3,
+            ..ChunkBatchOptions::default()
+        },
+        ChunkBatchOptions {
+            max_bytes: 0,
+            pipelined: true,
+            ..ChunkBatchOptions::default()
+        },
+    ] {
+        file_center.set_chunk_batch_options(options);
+
+        assert_eq!(options, file_center.get_chunk_batch_options());
+
+        let file_id = file_center
+            .put_file_by_reader_temporarily(File::open(IMAGE_BIG_PATH).await.unwrap(), "", None)
+            .await
+            .unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(IMAGE_BIG_SIZE, file_item.get_file_size());
+        assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+        let file_id =
+            file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+        file_center.delete_file_item_by_id(file_id).await.unwrap();
+    }
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{FileCenter, FileData, UploadOptions};
+use tokio::{
+    fs::{self, File},
+    io,
+};
+
+#[tokio::test]
+async fn chunk_size() {
+    let uri = get_mongodb_uri("test_chunk_size");
+
+    let mut file_center = FileCenter::new(uri).await.unwrap();
+
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    file_center.set_file_size_threshold(IMAGE_BIG_SIZE as u32).await.unwrap();
+    file_center.set_chunk_size(65536).await.unwrap();
+    assert_eq!(65536, file_center.get_chunk_size());
+
+    {
+        let file_id = file_center
+            .put_file_by_reader_temporarily(File::open(IMAGE_BIG_PATH).await.unwrap(), "", None)
+            .await
+            .unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert!(matches!(file_item.into_file_data(), FileData::Buffer(_)));
+    }
+
+    {
+        let mut upload_stream = file_center
+            .open_upload_stream_temporarily("", None, UploadOptions::default())
+            .await
+            .unwrap();
+
+        io::copy(&mut File::open(IMAGE_BIG_PATH).await.unwrap(), &mut upload_stream).await.unwrap();
+
+        let file_id = upload_stream.finish().await.unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert!(matches!(file_item.into_file_data(), FileData::Buffer(_)));
+    }
+
+    file_center.set_file_size_threshold(100000).await.unwrap();
+
+    for chunk_size in [65536, 262144] {
+        file_center.set_chunk_size(chunk_size).await.unwrap();
+
+        {
+            let file_id = file_center
+                .put_file_by_reader_temporarily(File::open(IMAGE_BIG_PATH).await.unwrap(), "", None)
+                .await
+                .unwrap();
+
+            let file_data =
+                file_center.get_file_range_by_id(file_id, 70000..300000).await.unwrap().unwrap();
+
+            assert_eq!(&image_big[70000..300000], file_data.into_vec().await.unwrap());
+        }
+
+        {
+            let mut upload_stream = file_center
+                .open_upload_stream_temporarily("", None, UploadOptions::default())
+                .await
+                .unwrap();
+
+            io::copy(&mut File::open(IMAGE_BIG_PATH).await.unwrap(), &mut upload_stream)
+                .await
+                .unwrap();
+
+            let file_id = upload_stream.finish().await.unwrap();
+
+            let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+            assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+        }
+    }
+
+    assert!(file_center.set_chunk_size(0).await.is_err());
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{mongodb::Client, FileCenter};
+
+#[tokio::test]
+async fn client() {
+    let uri = get_mongodb_uri("test_client");
+
+    let client = Client::with_uri_str(&uri).await.unwrap();
+
+    let file_center_1 = FileCenter::new_with_client(client.clone(), "test_client").await.unwrap();
+    let file_center_2 =
+        FileCenter::new_with_database(client.database("test_client")).await.unwrap();
+
+    let file_id =
+        file_center_1.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+
+    // both file centers use the same collections in the same database
+    let file_item = file_center_2.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    assert_eq!(IMAGE_SMALL_SIZE, file_item.get_file_size());
+
+    // the database name is taken from the URI
+    let file_center_3 = FileCenter::new(&uri).await.unwrap();
+
+    assert!(file_center_3.check_file_item_exist(file_id).await.unwrap());
+
+    file_center_1.drop_database().await.unwrap();
+}
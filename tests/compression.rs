@@ -0,0 +1,121 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{mime, Compression, ContentEncoding, FileCenter, UploadOptions};
+use tokio::{fs, io::AsyncWriteExt};
+
+#[tokio::test]
+async fn compression() {
+    let uri = get_mongodb_uri("test_compression");
+
+    let file_center = FileCenter::new_with_file_size_threshold(uri, 65536).await.unwrap();
+
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    let text_small = "Hello, world!\n".repeat(1000).into_bytes();
+    let text_big = (0..100000).map(|i| format!("{}\n", i)).collect::<String>().into_bytes();
+
+    for content_encoding in [ContentEncoding::Gzip, ContentEncoding::Zstd] {
+        let upload_options = UploadOptions {
+            compression: Some(Compression::Always(content_encoding)),
+            ..UploadOptions::default()
+        };
+
+        for text in [&text_small, &text_big] {
+            let file_id = file_center
+                .put_file_by_buffer_with_options(
+                    text.as_slice(),
+                    "",
+                    Some(mime::TEXT_PLAIN),
+                    upload_options.clone(),
+                )
+                .await
+                .unwrap();
+
+            let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+            assert_eq!(text.len() as u64, file_item.get_file_size());
+            assert_eq!(Some(content_encoding), file_item.get_content_encoding());
+
+            let raw_data = file_item.into_raw_file_data().into_vec().await.unwrap();
+
+            assert!(raw_data.len() < text.len());
+
+            let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+            assert_eq!(text, &file_item.into_file_data().into_vec().await.unwrap());
+
+            let data = file_center
+                .get_file_range_by_id(file_id, 5000..=12000)
+                .await
+                .unwrap()
+                .unwrap()
+                .into_vec()
+                .await
+                .unwrap();
+
+            assert_eq!(&text[5000..=12000], data.as_slice());
+
+            // the same content is deduplicated no matter how it is stored
+            let file_id_2 =
+                file_center.put_file_by_buffer(text.as_slice(), "", None).await.unwrap();
+
+            assert_eq!(
+                2,
+                file_center.get_file_info_by_id(file_id_2).await.unwrap().unwrap().get_count()
+            );
+
+            file_center.delete_file_item_by_id(file_id).await.unwrap();
+            file_center.delete_file_item_by_id(file_id_2).await.unwrap();
+        }
+
+        let mut upload_stream = file_center
+            .open_upload_stream_temporarily("", Some(mime::TEXT_PLAIN), upload_options.clone())
+            .await
+            .unwrap();
+
+        upload_stream.write_all(&text_big).await.unwrap();
+
+        let file_id = upload_stream.finish().await.unwrap();
+
+        assert!(file_center.persist_temporary_file(file_id).await.unwrap());
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(text_big, file_item.into_file_data().into_vec().await.unwrap());
+
+        file_center.delete_file_item_by_id(file_id).await.unwrap();
+    }
+
+    let upload_options = UploadOptions {
+        compression: Some(Compression::ByMimeType(ContentEncoding::Gzip)),
+        ..UploadOptions::default()
+    };
+
+    let file_id = file_center
+        .put_file_by_path_with_options(IMAGE_BIG_PATH, None::<&str>, None, upload_options.clone())
+        .await
+        .unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    assert_eq!(None, file_item.get_content_encoding());
+    assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+    let file_id = file_center
+        .put_file_by_buffer_temporarily_with_options(
+            text_big.as_slice(),
+            "",
+            Some(mime::TEXT_CSV),
+            upload_options,
+        )
+        .await
+        .unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    assert_eq!(Some(ContentEncoding::Gzip), file_item.get_content_encoding());
+    assert_eq!(text_big, file_item.into_file_data().into_vec().await.unwrap());
+
+    file_center.drop_database().await.unwrap();
+}
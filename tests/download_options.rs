@@ -0,0 +1,40 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{DownloadOptions, FileCenter};
+use tokio::fs;
+
+#[tokio::test]
+async fn download_options() {
+    let uri = get_mongodb_uri("test_download_options");
+
+    let mut file_center = FileCenter::new_with_file_size_threshold(uri, 65536).await.unwrap();
+
+    let options = DownloadOptions {
+        batch_size: Some(2), read_ahead: 4
+    };
+
+    file_center.set_download_options(options);
+
+    assert_eq!(options, file_center.get_download_options());
+
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    let file_id = file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+    let file_data =
+        file_center.get_file_range_by_id(file_id, 100000..900000).await.unwrap().unwrap();
+
+    assert_eq!(&image_big[100000..900000], file_data.into_vec().await.unwrap());
+
+    // dropping a stream which is being read ahead
+    let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    drop(file_item);
+
+    file_center.drop_database().await.unwrap();
+}
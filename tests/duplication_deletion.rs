@@ -23,11 +23,14 @@ async fn duplication_deletion() {
         let file_id_2 =
             file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_none());
+        assert!(!file_center.check_file_item_exist(file_id_1).await.unwrap());
+        assert!(file_center.get_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_none());
     }
 
     {
@@ -40,11 +43,14 @@ async fn duplication_deletion() {
         let file_id_2 =
             file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_none());
+        assert!(!file_center.check_file_item_exist(file_id_1).await.unwrap());
+        assert!(file_center.get_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_none());
     }
 
     {
@@ -57,11 +63,14 @@ async fn duplication_deletion() {
         let file_id_2 =
             file_center.put_file_by_buffer(image_small.clone(), "", None).await.unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_none());
+        assert!(!file_center.check_file_item_exist(file_id_1).await.unwrap());
+        assert!(file_center.get_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_none());
     }
 
     {
@@ -72,11 +81,14 @@ async fn duplication_deletion() {
 
         let file_id_2 = file_center.put_file_by_buffer(image_big.clone(), "", None).await.unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_none());
+        assert!(!file_center.check_file_item_exist(file_id_1).await.unwrap());
+        assert!(file_center.get_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_none());
     }
 
     {
@@ -93,11 +105,14 @@ async fn duplication_deletion() {
             .await
             .unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_none());
+        assert!(!file_center.check_file_item_exist(file_id_1).await.unwrap());
+        assert!(file_center.get_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_none());
     }
 
     {
@@ -114,11 +129,14 @@ async fn duplication_deletion() {
             .await
             .unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_none());
+        assert!(!file_center.check_file_item_exist(file_id_1).await.unwrap());
+        assert!(file_center.get_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_some());
+        assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_none());
     }
 
     file_center.drop_database().await.unwrap();
@@ -141,9 +159,9 @@ async fn duplication_different_threshold() {
         let file_id_2 =
             file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        let file_item = file_center.get_file_item_by_id(file_id_1).await.unwrap().unwrap();
+        let file_item = file_center.get_file_item_by_id(file_id_2).await.unwrap().unwrap();
 
         assert!(matches!(file_item.into_file_data(), FileData::Buffer(_)));
     }
@@ -159,9 +177,9 @@ async fn duplication_different_threshold() {
         let file_id_2 =
             file_center.put_file_by_buffer(image_small.clone(), "", None).await.unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        let file_item = file_center.get_file_item_by_id(file_id_1).await.unwrap().unwrap();
+        let file_item = file_center.get_file_item_by_id(file_id_2).await.unwrap().unwrap();
 
         assert!(matches!(file_item.into_file_data(), FileData::Buffer(_)));
     }
@@ -181,9 +199,9 @@ async fn duplication_different_threshold() {
             .await
             .unwrap();
 
-        assert_eq!(file_id_1, file_id_2);
+        assert_ne!(file_id_1, file_id_2);
 
-        let file_item = file_center.get_file_item_by_id(file_id_1).await.unwrap().unwrap();
+        let file_item = file_center.get_file_item_by_id(file_id_2).await.unwrap().unwrap();
 
         assert!(matches!(file_item.into_file_data(), FileData::Buffer(_)));
     }
@@ -0,0 +1,141 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{
+    bson::{doc, Document},
+    mime,
+    mongodb::Client,
+    Compression, ContentEncoding, Encryption, EncryptionAlgorithm, EncryptionKey, FileCenter,
+    FileCenterError, UploadOptions, COLLECTION_FILES_CHUNKS_NAME,
+};
+use tokio::{
+    fs::{self, File},
+    io,
+};
+
+#[tokio::test]
+async fn encryption() {
+    let uri = get_mongodb_uri("test_encryption");
+
+    let mut file_center = FileCenter::new_with_file_size_threshold(&uri, 65536).await.unwrap();
+
+    let collection_files_chunks = Client::with_uri_str(&uri)
+        .await
+        .unwrap()
+        .database("test_encryption")
+        .collection::<Document>(COLLECTION_FILES_CHUNKS_NAME);
+
+    let image_small = fs::read(IMAGE_SMALL_PATH).await.unwrap();
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    let key_1 = EncryptionKey::new("key-1", EncryptionAlgorithm::Aes256Gcm, [1; 32]);
+    let key_2 = EncryptionKey::new("key-2", EncryptionAlgorithm::ChaCha20Poly1305, [2; 32]);
+
+    file_center.set_encryption(Some(Encryption::new(key_1.clone())));
+
+    let file_id_1 =
+        file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+    let file_id_2 = file_center
+        .put_file_by_reader_temporarily(File::open(IMAGE_BIG_PATH).await.unwrap(), "", None)
+        .await
+        .unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id_2).await.unwrap().unwrap();
+
+    assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+    let data = file_center
+        .get_file_range_by_id(file_id_1, 100..2000)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_vec()
+        .await
+        .unwrap();
+
+    assert_eq!(&image_small[100..2000], data.as_slice());
+
+    // rotate the key, and keep the old one for decryption
+    let mut encryption = Encryption::new(key_2);
+
+    encryption.add_key(key_1);
+
+    file_center.set_encryption(Some(encryption));
+
+    let mut upload_stream = file_center
+        .open_upload_stream("", Some(mime::TEXT_PLAIN), UploadOptions {
+            compression: Some(Compression::Always(ContentEncoding::Zstd)),
+            ..UploadOptions::default()
+        })
+        .await
+        .unwrap();
+
+    io::copy(&mut File::open(IMAGE_BIG_PATH).await.unwrap(), &mut upload_stream).await.unwrap();
+
+    let file_id_3 = upload_stream.finish().await.unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id_3).await.unwrap().unwrap();
+
+    assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+    let data = file_center
+        .get_file_range_by_id(file_id_3, 300000..=900000)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_vec()
+        .await
+        .unwrap();
+
+    assert_eq!(&image_big[300000..=900000], data.as_slice());
+
+    let file_item = file_center.get_file_item_by_id(file_id_1).await.unwrap().unwrap();
+
+    assert_eq!(image_small, file_item.into_file_data().into_vec().await.unwrap());
+
+    // the content is not deduplicated into the content encrypted with another key
+    let file_id_4 =
+        file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+
+    assert_eq!(1, file_center.get_file_info_by_id(file_id_1).await.unwrap().unwrap().get_count());
+    assert_eq!(1, file_center.get_file_info_by_id(file_id_4).await.unwrap().unwrap().get_count());
+
+    // the chunks cannot be reordered
+    for (from, to) in [(0i64, -1i64), (1, 0), (-1, 1)] {
+        collection_files_chunks
+            .update_one(
+                doc! {
+                    "file_id": file_id_3,
+                    "n": from
+                },
+                doc! {
+                    "$set": {
+                        "n": to
+                    }
+                },
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    let file_item = file_center.get_file_item_by_id(file_id_3).await.unwrap().unwrap();
+
+    assert!(file_item.into_file_data().into_vec().await.is_err());
+
+    // the files cannot be read without the keys
+    file_center.set_encryption(None);
+
+    // the content is not deduplicated into the encrypted content
+    let file_id_5 =
+        file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+
+    assert_eq!(1, file_center.get_file_info_by_id(file_id_5).await.unwrap().unwrap().get_count());
+
+    match file_center.get_file_item_by_id(file_id_3).await {
+        Err(FileCenterError::EncryptionKeyNotFoundError(key_id)) => assert_eq!("key-2", key_id),
+        _ => panic!("the encrypted file should not be read without the key"),
+    }
+
+    file_center.drop_database().await.unwrap();
+}
@@ -1,9 +1,11 @@
 mod common;
 
+use std::time::Duration;
+
 use common::*;
 use mongo_file_center::{
-    bson::{doc, Document},
-    FileCenter, COLLECTION_FILES_CHUNKS_NAME, COLLECTION_FILES_NAME,
+    bson::{doc, oid::ObjectId, spec::BinarySubtype, Binary, DateTime, Document},
+    FileCenter, GarbageOptions, COLLECTION_FILES_CHUNKS_NAME, COLLECTION_FILES_NAME,
 };
 
 #[tokio::test]
@@ -41,7 +43,25 @@ async fn garbage() {
 
         assert!(file_center.get_file_item_by_id(file_id_3).await.unwrap().is_some());
 
-        file_center.clear_garbage().await.unwrap();
+        let report = file_center
+            .clear_garbage_with_options(GarbageOptions {
+                dry_run: true,
+                ..GarbageOptions::default()
+            })
+            .await
+            .unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(vec![file_id_3], report.orphan_items);
+
+        assert!(file_center.check_file_item_exist(file_id_3).await.unwrap());
+
+        let report = file_center.clear_garbage().await.unwrap();
+
+        assert!(!report.dry_run);
+        assert_eq!(vec![file_id_3], report.orphan_items);
+        assert!(report.zero_count_items.is_empty());
+        assert!(report.orphan_chunk_file_ids.is_empty());
 
         assert!(file_center.get_file_item_by_id(file_id_1).await.unwrap().is_some());
         assert!(file_center.get_file_item_by_id(file_id_2).await.unwrap().is_some());
@@ -81,7 +101,33 @@ async fn garbage() {
             .await
             .unwrap();
 
-        file_center.clear_garbage().await.unwrap();
+        // the chunks of file 3 are young, so they are kept within the grace period
+        assert!(file_center.clear_garbage().await.unwrap().is_empty());
+
+        let options = GarbageOptions {
+            grace_period: Duration::ZERO,
+            ..GarbageOptions::default()
+        };
+
+        let report = file_center
+            .clear_garbage_with_options(GarbageOptions {
+                dry_run: true,
+                ..options
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(vec![file_id_3], report.orphan_chunk_file_ids);
+        assert_eq!(IMAGE_BIG_SIZE, report.reclaimed_bytes);
+
+        let report = file_center.clear_garbage_with_options(options).await.unwrap();
+
+        assert_eq!(1, report.orphan_chunk_file_count);
+        assert_eq!(vec![file_id_3], report.orphan_chunk_file_ids);
+        assert!(report.removed_chunks > 0);
+        assert_eq!(IMAGE_BIG_SIZE, report.reclaimed_bytes);
+
+        assert!(file_center.clear_garbage_with_options(options).await.unwrap().is_empty());
 
         assert!(collection_files_chunks
             .find_one(
@@ -102,3 +148,96 @@ async fn garbage() {
 
     file_center.drop_database().await.unwrap();
 }
+
+#[tokio::test]
+async fn garbage_long_upload() {
+    let uri = get_mongodb_uri("test_garbage_long_upload");
+
+    let file_center = FileCenter::new(&uri).await.unwrap();
+
+    let db = unsafe { file_center.database() };
+
+    let collection_files_chunks = db.collection::<Document>(COLLECTION_FILES_CHUNKS_NAME);
+
+    // the upload started two hours ago, which is longer than the grace period
+    let mut bytes = ObjectId::new().bytes();
+
+    let timestamp = (DateTime::now().timestamp_millis() / 1000 - 7200) as u32;
+
+    bytes[..4].copy_from_slice(&timestamp.to_be_bytes());
+
+    let file_id = ObjectId::from_bytes(bytes);
+
+    let chunk = |n: i64, create_time: DateTime| {
+        doc! {
+            "file_id": file_id,
+            "n": n,
+            "data": Binary {
+                subtype: BinarySubtype::Generic, bytes: vec![0u8; 16]
+            },
+            "create_time": create_time,
+        }
+    };
+
+    collection_files_chunks
+        .insert_many(
+            [
+                chunk(0, DateTime::from_millis(DateTime::now().timestamp_millis() - 7_200_000)),
+                chunk(1, DateTime::now()),
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+
+    // the upload is still inserting chunks, so none of them is removed
+    assert!(file_center.clear_garbage().await.unwrap().is_empty());
+
+    assert_eq!(
+        2,
+        collection_files_chunks
+            .count_documents(
+                doc! {
+                    "file_id": file_id
+                },
+                None
+            )
+            .await
+            .unwrap()
+    );
+
+    // the upload has been abandoned
+    collection_files_chunks
+        .update_many(
+            doc! {
+                "file_id": file_id
+            },
+            doc! {
+                "$set": {
+                    "create_time": DateTime::from_millis(DateTime::now().timestamp_millis() - 7_200_000)
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let report = file_center.clear_garbage().await.unwrap();
+
+    assert_eq!(vec![file_id], report.orphan_chunk_file_ids);
+
+    assert_eq!(
+        0,
+        collection_files_chunks
+            .count_documents(
+                doc! {
+                    "file_id": file_id
+                },
+                None
+            )
+            .await
+            .unwrap()
+    );
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use std::time::Duration;
+
+use common::*;
+use mongo_file_center::{
+    bson::{doc, Document},
+    FileCenter, IncrementalGarbageOptions, COLLECTION_FILES_CHUNKS_NAME, COLLECTION_FILES_NAME,
+};
+
+#[tokio::test]
+async fn garbage_incremental() {
+    let uri = get_mongodb_uri("test_garbage_incremental");
+
+    let file_center = FileCenter::new(&uri).await.unwrap();
+
+    let db = unsafe { file_center.database() };
+
+    let collection_files = db.collection::<Document>(COLLECTION_FILES_NAME);
+    let collection_files_chunks = db.collection::<Document>(COLLECTION_FILES_CHUNKS_NAME);
+
+    let file_id_1 =
+        file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+    let file_id_2 = file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+    let file_id_3 = file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+    let file_id_4 =
+        file_center.put_file_by_path_temporarily(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+    // the chunks of file 2 become orphans
+    collection_files
+        .delete_one(
+            doc! {
+                "_id": file_id_2
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // the chunks of temporary file 4 are left to the TTL index
+    collection_files
+        .delete_one(
+            doc! {
+                "_id": file_id_4
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // file 3 is no longer used
+    collection_files
+        .update_one(
+            doc! {
+                "_id": file_id_3
+            },
+            doc! {
+                "$set": {
+                    "count": 0
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // the chunks of file 2 are young, so they are kept within the grace period
+    let report = file_center
+        .clear_garbage_incrementally(IncrementalGarbageOptions::default())
+        .await
+        .unwrap();
+
+    assert!(report.completed);
+    assert_eq!(1, report.zero_count_item_count);
+    assert_eq!(vec![file_id_3], report.zero_count_items);
+    assert!(report.orphan_chunk_file_ids.is_empty());
+
+    let options = IncrementalGarbageOptions {
+        batch_size: 1,
+        max_batches: Some(1),
+        grace_period: Duration::ZERO,
+        ..IncrementalGarbageOptions::default()
+    };
+
+    // resume from the checkpoint until the whole file center has been scanned
+    let mut orphan_chunk_file_ids = Vec::new();
+    let mut runs = 0;
+
+    loop {
+        let report = file_center.clear_garbage_incrementally(options).await.unwrap();
+
+        orphan_chunk_file_ids.extend(report.orphan_chunk_file_ids);
+        runs += 1;
+
+        if report.completed {
+            break;
+        }
+    }
+
+    assert!(runs > 1);
+    assert_eq!(vec![file_id_2], orphan_chunk_file_ids);
+
+    assert!(collection_files_chunks
+        .find_one(
+            doc! {
+                "file_id": file_id_2
+            },
+            None
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    assert!(collection_files_chunks
+        .find_one(
+            doc! {
+                "file_id": file_id_4
+            },
+            None
+        )
+        .await
+        .unwrap()
+        .is_some());
+
+    assert!(file_center.get_file_item_by_id(file_id_1).await.unwrap().is_some());
+
+    file_center.drop_database().await.unwrap();
+}
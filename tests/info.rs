@@ -0,0 +1,51 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{mime, FileCenter};
+
+#[tokio::test]
+async fn info() {
+    let uri = get_mongodb_uri("test_info");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    {
+        let file_id = file_center
+            .put_file_by_path(IMAGE_BIG_PATH, Some("image.jpg"), Some(mime::IMAGE_JPEG))
+            .await
+            .unwrap();
+
+        let file_info = file_center.get_file_info_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(file_id, file_info.get_file_id());
+        assert_eq!("image.jpg", file_info.get_file_name());
+        assert_eq!(&mime::IMAGE_JPEG, file_info.get_mime_type());
+        assert_eq!(IMAGE_BIG_SIZE, file_info.get_file_size());
+        assert_eq!(1, file_info.get_count());
+        assert!(file_info.get_expiration_time().is_none());
+
+        file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+        let file_info = file_center.get_file_info_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(2, file_info.get_count());
+    }
+
+    {
+        let file_id = file_center
+            .put_file_by_path_temporarily(IMAGE_SMALL_PATH, None::<&str>, None)
+            .await
+            .unwrap();
+
+        let file_info = file_center.get_file_info_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(IMAGE_SMALL_SIZE, file_info.get_file_size());
+        assert!(file_info.get_expiration_time().is_some());
+
+        assert!(file_center.get_file_info_by_id(file_id).await.unwrap().is_some());
+        assert!(file_center.get_file_item_by_id(file_id).await.unwrap().is_some());
+        assert!(file_center.get_file_info_by_id(file_id).await.unwrap().is_none());
+    }
+
+    file_center.drop_database().await.unwrap();
+}
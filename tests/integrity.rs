@@ -0,0 +1,117 @@
+mod common;
+
+use std::io;
+
+use common::*;
+use mongo_file_center::{
+    bson::{doc, spec::BinarySubtype, Binary, Document},
+    mongodb::Client,
+    FileCenter, IntegrityError, COLLECTION_FILES_CHUNKS_NAME,
+};
+use tokio::fs;
+
+fn get_integrity_error(err: &io::Error) -> Option<&IntegrityError> {
+    err.get_ref().and_then(|inner| inner.downcast_ref::<IntegrityError>())
+}
+
+#[tokio::test]
+async fn integrity() {
+    let uri = get_mongodb_uri("test_integrity");
+
+    let mut file_center = FileCenter::new_with_file_size_threshold(&uri, 65536).await.unwrap();
+
+    file_center.set_chunk_size(65536).await.unwrap();
+
+    let collection_files_chunks = Client::with_uri_str(&uri)
+        .await
+        .unwrap()
+        .database("test_integrity")
+        .collection::<Document>(COLLECTION_FILES_CHUNKS_NAME);
+
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    let file_id = file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+    // corrupt the data of a chunk
+    collection_files_chunks
+        .update_one(
+            doc! {
+                "file_id": file_id,
+                "n": 1i64
+            },
+            doc! {
+                "$set": {
+                    "data": Binary {
+                        subtype: BinarySubtype::Generic, bytes: vec![0; 65536]
+                    }
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    let err = file_item.into_file_data().into_vec().await.unwrap_err();
+
+    assert_eq!(
+        Some(&IntegrityError::ChunkChecksumMismatch {
+            n: 1
+        }),
+        get_integrity_error(&err)
+    );
+
+    let err = file_center
+        .get_file_range_by_id(file_id, 65536..70000)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_vec()
+        .await
+        .unwrap_err();
+
+    assert!(get_integrity_error(&err).is_some());
+
+    // remove a chunk in the middle and the last chunk
+    collection_files_chunks
+        .delete_many(
+            doc! {
+                "file_id": file_id,
+                "n": {
+                    "$in": [1i64, (IMAGE_BIG_SIZE / 65536) as i64]
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    let err = file_item.into_file_data().into_vec().await.unwrap_err();
+
+    assert_eq!(
+        Some(&IntegrityError::MissingChunk {
+            n: 1
+        }),
+        get_integrity_error(&err)
+    );
+
+    let data = file_center
+        .get_file_range_by_id(file_id, 0..65536)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_vec()
+        .await
+        .unwrap();
+
+    assert_eq!(&image_big[..65536], data.as_slice());
+
+    file_center.drop_database().await.unwrap();
+}
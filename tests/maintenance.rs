@@ -0,0 +1,183 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::*;
+use mongo_file_center::{
+    bson::{doc, DateTime, Document},
+    mongodb::Client,
+    Expiration, FileCenter, IncrementalGarbageOptions, MaintenanceOptions, UploadOptions,
+    COLLECTION_FILES_CHUNKS_NAME, COLLECTION_FILES_NAME, COLLECTION_SETTINGS_NAME,
+    SETTING_MAINTENANCE_LEASE,
+};
+
+#[tokio::test]
+async fn maintenance() {
+    let uri = get_mongodb_uri("test_maintenance");
+
+    let file_center = Arc::new(FileCenter::new(&uri).await.unwrap());
+
+    let db = Client::with_uri_str(&uri).await.unwrap().database("test_maintenance");
+    let collection_files = db.collection::<Document>(COLLECTION_FILES_NAME);
+    let collection_files_chunks = db.collection::<Document>(COLLECTION_FILES_CHUNKS_NAME);
+    let collection_settings = db.collection::<Document>(COLLECTION_SETTINGS_NAME);
+
+    let file_id_1 =
+        file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+    let file_id_2 = file_center
+        .put_file_by_path_temporarily_with_options(
+            IMAGE_SMALL_PATH,
+            None::<&str>,
+            None,
+            UploadOptions {
+                expiration: Some(Expiration::After(Duration::from_millis(1))),
+                ..UploadOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let file_id_6 = file_center
+        .put_file_by_buffer_temporarily_with_options(vec![3u8; 16], "", None, UploadOptions {
+            expiration: Some(Expiration::After(Duration::from_millis(1))),
+            ..UploadOptions::default()
+        })
+        .await
+        .unwrap();
+
+    // the expired files are removed one batch at a time
+    let options = MaintenanceOptions {
+        garbage_interval: None,
+        expired_sweep_interval: Some(Duration::from_millis(100)),
+        batch_size: 1,
+        jitter: Duration::from_millis(10),
+        ..MaintenanceOptions::default()
+    };
+
+    let handle_1 = file_center.clone().spawn_maintenance(options);
+    let handle_2 = file_center.clone().spawn_maintenance(options);
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    assert!(collection_files
+        .find_one(
+            doc! {
+                "_id": file_id_2
+            },
+            None
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    assert!(collection_files
+        .find_one(
+            doc! {
+                "_id": file_id_6
+            },
+            None
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    assert!(collection_settings
+        .find_one(
+            doc! {
+                "_id": SETTING_MAINTENANCE_LEASE
+            },
+            None
+        )
+        .await
+        .unwrap()
+        .is_some());
+
+    handle_1.shutdown().await.unwrap();
+    handle_2.shutdown().await.unwrap();
+
+    // the lease is released
+    assert!(collection_settings
+        .find_one(
+            doc! {
+                "_id": SETTING_MAINTENANCE_LEASE
+            },
+            None
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    assert!(file_center.check_file_item_exist(file_id_1).await.unwrap());
+
+    assert_eq!(1, file_center.delete_files_created_before(DateTime::now()).await.unwrap());
+
+    assert!(!file_center.check_file_item_exist(file_id_1).await.unwrap());
+
+    // the garbage is cleared one batch at a time
+    let file_id_3 = file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+    collection_files
+        .delete_one(
+            doc! {
+                "_id": file_id_3
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let options = MaintenanceOptions {
+        garbage_interval: Some(Duration::from_millis(100)),
+        garbage_options: IncrementalGarbageOptions {
+            batch_size: 1,
+            max_batches: Some(2),
+            grace_period: Duration::ZERO,
+            ..IncrementalGarbageOptions::default()
+        },
+        expired_sweep_interval: None,
+        jitter: Duration::from_millis(10),
+        ..MaintenanceOptions::default()
+    };
+
+    let handle = file_center.clone().spawn_maintenance(options);
+
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    handle.shutdown().await.unwrap();
+
+    assert!(collection_files_chunks
+        .find_one(
+            doc! {
+                "file_id": file_id_3
+            },
+            None
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    // the retention policy is enforced one batch at a time
+    let file_id_4 = file_center.put_file_by_buffer(vec![1u8; 16], "", None).await.unwrap();
+    let file_id_5 = file_center.put_file_by_buffer(vec![2u8; 16], "", None).await.unwrap();
+
+    let options = MaintenanceOptions {
+        garbage_interval: None,
+        expired_sweep_interval: None,
+        retention: Some(Duration::ZERO),
+        retention_interval: Duration::from_millis(100),
+        batch_size: 1,
+        jitter: Duration::from_millis(10),
+        ..MaintenanceOptions::default()
+    };
+
+    let handle = file_center.clone().spawn_maintenance(options);
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    handle.shutdown().await.unwrap();
+
+    assert!(!file_center.check_file_item_exist(file_id_4).await.unwrap());
+    assert!(!file_center.check_file_item_exist(file_id_5).await.unwrap());
+
+    Arc::try_unwrap(file_center).unwrap().drop_database().await.unwrap();
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{
+    bson::{doc, spec::BinarySubtype, Binary},
+    FileCenter, FileCenterError, FileQuery, StreamExt, UploadOptions, MAX_FILE_SIZE_THRESHOLD,
+};
+use tokio::fs::File;
+
+#[tokio::test]
+async fn metadata() {
+    let uri = get_mongodb_uri("test_metadata");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    {
+        let file_id = file_center
+            .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, UploadOptions {
+                metadata: Some(doc! {
+                    "owner": "alice",
+                    "width": 100
+                }),
+                ..UploadOptions::default()
+            })
+            .await
+            .unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(
+            Some(&doc! {
+                "owner": "alice",
+                "width": 100
+            }),
+            file_item.get_metadata()
+        );
+
+        let file_ids = file_center
+            .find_file_infos(FileQuery {
+                metadata: Some(doc! {
+                    "owner": "alice"
+                }),
+                ..FileQuery::default()
+            })
+            .await
+            .unwrap()
+            .map(|info| info.unwrap().get_file_id())
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(vec![file_id], file_ids);
+
+        assert!(file_center
+            .set_file_metadata(
+                file_id,
+                Some(doc! {
+                    "owner": "bob"
+                })
+            )
+            .await
+            .unwrap());
+
+        let file_info = file_center.get_file_info_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(
+            Some(&doc! {
+                "owner": "bob"
+            }),
+            file_info.get_metadata()
+        );
+
+        assert!(file_center.set_file_metadata(file_id, None).await.unwrap());
+
+        let file_info = file_center.get_file_info_by_id(file_id).await.unwrap().unwrap();
+
+        assert!(file_info.get_metadata().is_none());
+    }
+
+    {
+        let file_id = file_center
+            .put_file_by_reader_temporarily_with_options(
+                File::open(IMAGE_BIG_PATH).await.unwrap(),
+                "",
+                None,
+                UploadOptions {
+                    metadata: Some(doc! {
+                        "tags": ["a", "b"]
+                    }),
+                    ..UploadOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(
+            Some(&doc! {
+                "tags": ["a", "b"]
+            }),
+            file_item.get_metadata()
+        );
+    }
+
+    file_center.drop_database().await.unwrap();
+}
+
+#[tokio::test]
+async fn metadata_size() {
+    let uri = get_mongodb_uri("test_metadata_size");
+
+    let file_center =
+        FileCenter::new_with_file_size_threshold(uri, MAX_FILE_SIZE_THRESHOLD).await.unwrap();
+
+    let upload_options = |size: usize| UploadOptions {
+        metadata: Some(doc! {
+            "thumbnail": Binary {
+                subtype: BinarySubtype::Generic, bytes: vec![0u8; size]
+            }
+        }),
+        ..UploadOptions::default()
+    };
+
+    // the data does not fit in the file item along with the metadata, so it is separated into chunks
+    let data = vec![1u8; 2_000_000];
+
+    let file_id = file_center
+        .put_file_by_buffer_with_options(data.clone(), "", None, upload_options(15_000_000))
+        .await
+        .unwrap();
+
+    let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+    assert!(file_item.get_metadata().is_some());
+    assert_eq!(data, file_item.into_file_data().into_vec().await.unwrap());
+
+    // the metadata alone is too large
+    assert!(matches!(
+        file_center
+            .put_file_by_buffer_with_options(
+                data,
+                "",
+                None,
+                upload_options(MAX_FILE_SIZE_THRESHOLD as usize)
+            )
+            .await,
+        Err(FileCenterError::MetadataTooLargeError)
+    ));
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{bson::oid::ObjectId, FileCenter};
+use tokio::fs;
+
+#[tokio::test]
+async fn persist() {
+    let uri = get_mongodb_uri("test_persist");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    assert!(!file_center.persist_temporary_file(ObjectId::new()).await.unwrap());
+
+    let file_id_1 = file_center
+        .put_file_by_path_temporarily(IMAGE_BIG_PATH, Some("first.jpg"), None)
+        .await
+        .unwrap();
+
+    assert!(file_center.persist_temporary_file(file_id_1).await.unwrap());
+    assert!(!file_center.persist_temporary_file(file_id_1).await.unwrap());
+
+    for _ in 0..2 {
+        let file_item = file_center.get_file_item_by_id(file_id_1).await.unwrap().unwrap();
+
+        assert!(file_item.get_expiration_time().is_none());
+        assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+    }
+
+    let file_id_2 = file_center
+        .put_file_by_buffer_temporarily(image_big.clone(), "second.jpg", None)
+        .await
+        .unwrap();
+
+    assert!(file_center.persist_temporary_file(file_id_2).await.unwrap());
+
+    {
+        let file_info = file_center.get_file_info_by_id(file_id_2).await.unwrap().unwrap();
+
+        assert_eq!("second.jpg", file_info.get_file_name());
+        assert_eq!(2, file_info.get_count());
+        assert!(file_info.get_expiration_time().is_none());
+
+        let file_item = file_center.get_file_item_by_id(file_id_2).await.unwrap().unwrap();
+
+        assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+    }
+
+    let file_id_3 =
+        file_center.put_file_by_buffer_temporarily(b"Hello", "hello.txt", None).await.unwrap();
+
+    assert!(file_center.persist_temporary_file(file_id_3).await.unwrap());
+
+    let file_id_4 = file_center.put_file_by_buffer(b"Hello", "hello-2.txt", None).await.unwrap();
+
+    assert_ne!(file_id_3, file_id_4);
+    assert_eq!(2, file_center.get_file_info_by_id(file_id_4).await.unwrap().unwrap().get_count());
+
+    file_center.drop_database().await.unwrap();
+}
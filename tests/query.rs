@@ -0,0 +1,104 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{mime, FileCenter, FileQuery, StreamExt};
+
+#[tokio::test]
+async fn query() {
+    let uri = get_mongodb_uri("test_query");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    let file_id_1 = file_center
+        .put_file_by_path(IMAGE_SMALL_PATH, Some("small.png"), Some(mime::IMAGE_PNG))
+        .await
+        .unwrap();
+    let file_id_2 = file_center
+        .put_file_by_path(IMAGE_BIG_PATH, Some("big.jpg"), Some(mime::IMAGE_JPEG))
+        .await
+        .unwrap();
+    let file_id_3 =
+        file_center.put_file_by_buffer_temporarily(b"Hello", "hello.txt", None).await.unwrap();
+
+    let collect = |query: FileQuery| {
+        let file_center = &file_center;
+
+        async move {
+            file_center
+                .find_file_infos(query)
+                .await
+                .unwrap()
+                .map(|info| info.unwrap().get_file_id())
+                .collect::<Vec<_>>()
+                .await
+        }
+    };
+
+    assert_eq!(vec![file_id_1, file_id_2, file_id_3], collect(FileQuery::default()).await);
+
+    assert_eq!(
+        vec![file_id_1, file_id_2],
+        collect(FileQuery {
+            mime_type_prefix: Some("image/".into()),
+            ..FileQuery::default()
+        })
+        .await
+    );
+
+    assert_eq!(
+        vec![file_id_2],
+        collect(FileQuery {
+            mime_type: Some(mime::IMAGE_JPEG),
+            ..FileQuery::default()
+        })
+        .await
+    );
+
+    assert_eq!(
+        vec![file_id_1],
+        collect(FileQuery {
+            file_name_pattern: Some(r"\.png$".into()),
+            ..FileQuery::default()
+        })
+        .await
+    );
+
+    assert_eq!(
+        vec![file_id_2],
+        collect(FileQuery {
+            min_file_size: Some(IMAGE_SMALL_SIZE + 1),
+            ..FileQuery::default()
+        })
+        .await
+    );
+
+    assert_eq!(
+        vec![file_id_3],
+        collect(FileQuery {
+            temporary: Some(true),
+            ..FileQuery::default()
+        })
+        .await
+    );
+
+    assert_eq!(
+        vec![file_id_1],
+        collect(FileQuery {
+            limit: Some(1),
+            ..FileQuery::default()
+        })
+        .await
+    );
+
+    assert_eq!(
+        vec![file_id_2],
+        collect(FileQuery {
+            after: Some(file_id_1),
+            limit: Some(1),
+            ..FileQuery::default()
+        })
+        .await
+    );
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,257 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{FileCenter, FileCenterError, Quota, UploadOptions, Usage};
+use tokio::{fs::File, io::AsyncWriteExt};
+
+fn owned_by(owner: &str) -> UploadOptions {
+    UploadOptions {
+        owner: Some(owner.to_string()),
+        ..UploadOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn quota() {
+    let uri = get_mongodb_uri("test_quota");
+
+    let file_center = FileCenter::new_with_file_size_threshold(&uri, 65536).await.unwrap();
+
+    assert_eq!(Usage::default(), file_center.usage("alice").await.unwrap());
+
+    let file_id_1 = file_center
+        .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("alice"))
+        .await
+        .unwrap();
+
+    // deduplicated content is counted with its full size for every owner
+    let file_id_2 = file_center
+        .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("bob"))
+        .await
+        .unwrap();
+
+    let file_id_3 = file_center
+        .put_file_by_path_temporarily_with_options(
+            IMAGE_BIG_PATH,
+            None::<&str>,
+            None,
+            owned_by("alice"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        Usage {
+            bytes: IMAGE_SMALL_SIZE + IMAGE_BIG_SIZE, files: 2
+        },
+        file_center.usage("alice").await.unwrap()
+    );
+    assert_eq!(
+        Usage {
+            bytes: IMAGE_SMALL_SIZE, files: 1
+        },
+        file_center.usage("bob").await.unwrap()
+    );
+
+    // a persisted temporary file is still counted
+    assert!(file_center.persist_temporary_file(file_id_3).await.unwrap());
+
+    assert_eq!(
+        Usage {
+            bytes: IMAGE_SMALL_SIZE + IMAGE_BIG_SIZE, files: 2
+        },
+        file_center.usage("alice").await.unwrap()
+    );
+
+    // the content is kept for bob, and it is no longer counted for alice
+    file_center.delete_file_item_by_id(file_id_1).await.unwrap();
+
+    assert_eq!(
+        Usage {
+            bytes: IMAGE_BIG_SIZE, files: 1
+        },
+        file_center.usage("alice").await.unwrap()
+    );
+    assert_eq!(
+        Usage {
+            bytes: IMAGE_SMALL_SIZE, files: 1
+        },
+        file_center.usage("bob").await.unwrap()
+    );
+    assert!(file_center.get_file_item_by_id(file_id_2).await.unwrap().is_some());
+
+    // the content is removed with the last file which refers to it
+    file_center.delete_file_item_by_id(file_id_2).await.unwrap();
+
+    assert_eq!(
+        Usage {
+            bytes: IMAGE_BIG_SIZE, files: 1
+        },
+        file_center.usage("alice").await.unwrap()
+    );
+    assert_eq!(Usage::default(), file_center.usage("bob").await.unwrap());
+
+    // enforce a quota
+    let quota = Quota {
+        max_bytes: Some(IMAGE_SMALL_SIZE * 2), max_files: None
+    };
+
+    file_center.set_quota("bob", Some(quota)).await.unwrap();
+
+    assert_eq!(Some(quota), file_center.get_quota("bob").await.unwrap());
+
+    file_center
+        .put_file_by_buffer_with_options(
+            vec![0u8; IMAGE_SMALL_SIZE as usize],
+            "",
+            None,
+            owned_by("bob"),
+        )
+        .await
+        .unwrap();
+
+    file_center
+        .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("bob"))
+        .await
+        .unwrap();
+
+    match file_center
+        .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("bob"))
+        .await
+    {
+        Err(FileCenterError::QuotaExceeded {
+            owner,
+        }) => assert_eq!("bob", owner),
+        _ => panic!("the quota should be exceeded"),
+    }
+
+    // a reader is stopped one byte after the remaining budget, and what has been stored is removed
+    match file_center
+        .put_file_by_reader_with_options(
+            File::open(IMAGE_BIG_PATH).await.unwrap(),
+            "",
+            None,
+            owned_by("bob"),
+        )
+        .await
+    {
+        Err(FileCenterError::QuotaExceeded {
+            ..
+        }) => (),
+        _ => panic!("the quota should be exceeded"),
+    }
+
+    assert!(file_center.clear_garbage().await.unwrap().is_empty());
+
+    // the data of a stream is rejected while it is written
+    let mut upload_stream =
+        file_center.open_upload_stream("", None, owned_by("bob")).await.unwrap();
+
+    assert!(upload_stream.write_all(&[0u8; 16]).await.is_err());
+
+    upload_stream.abort().await.unwrap();
+
+    file_center
+        .set_quota(
+            "bob",
+            Some(Quota {
+                max_bytes: None, max_files: Some(2)
+            }),
+        )
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        file_center.open_upload_stream("", None, owned_by("bob")).await,
+        Err(FileCenterError::QuotaExceeded { .. })
+    ));
+
+    assert_eq!(
+        Usage {
+            bytes: IMAGE_SMALL_SIZE * 2, files: 2
+        },
+        file_center.usage("bob").await.unwrap()
+    );
+
+    // the usage is kept after the quota is removed
+    file_center.set_quota("bob", None).await.unwrap();
+
+    assert_eq!(None, file_center.get_quota("bob").await.unwrap());
+    assert_eq!(2, file_center.usage("bob").await.unwrap().files);
+
+    file_center
+        .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("bob"))
+        .await
+        .unwrap();
+
+    file_center.drop_database().await.unwrap();
+}
+
+#[tokio::test]
+async fn quota_deduplicated() {
+    let uri = get_mongodb_uri("test_quota_deduplicated");
+
+    let file_center = FileCenter::new_with_file_size_threshold(&uri, 65536).await.unwrap();
+
+    let file_id_1 = file_center
+        .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("alice"))
+        .await
+        .unwrap();
+
+    let file_id_2 = file_center
+        .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("bob"))
+        .await
+        .unwrap();
+
+    let usage = Usage {
+        bytes: IMAGE_SMALL_SIZE, files: 1
+    };
+
+    assert_eq!(usage, file_center.usage("alice").await.unwrap());
+    assert_eq!(usage, file_center.usage("bob").await.unwrap());
+
+    // the owner of the reference deletes it
+    file_center.delete_file_item_by_id(file_id_2).await.unwrap();
+
+    assert_eq!(usage, file_center.usage("alice").await.unwrap());
+    assert_eq!(Usage::default(), file_center.usage("bob").await.unwrap());
+
+    // a quota which is full for bob is not affected by the content stored by alice
+    file_center
+        .set_quota(
+            "bob",
+            Some(Quota {
+                max_bytes: Some(IMAGE_SMALL_SIZE), max_files: None
+            }),
+        )
+        .await
+        .unwrap();
+
+    let file_id_3 = file_center
+        .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("bob"))
+        .await
+        .unwrap();
+
+    assert_eq!(usage, file_center.usage("bob").await.unwrap());
+
+    assert!(matches!(
+        file_center
+            .put_file_by_path_with_options(IMAGE_SMALL_PATH, None::<&str>, None, owned_by("bob"))
+            .await,
+        Err(FileCenterError::QuotaExceeded { .. })
+    ));
+
+    // the owner of the content deletes it
+    file_center.delete_file_item_by_id(file_id_1).await.unwrap();
+
+    assert_eq!(Usage::default(), file_center.usage("alice").await.unwrap());
+    assert_eq!(usage, file_center.usage("bob").await.unwrap());
+    assert!(file_center.get_file_item_by_id(file_id_3).await.unwrap().is_some());
+
+    file_center.delete_file_item_by_id(file_id_3).await.unwrap();
+
+    assert_eq!(Usage::default(), file_center.usage("bob").await.unwrap());
+    assert!(file_center.clear_garbage().await.unwrap().is_empty());
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::*;
+use mongo_file_center::FileCenter;
+use tokio::fs;
+
+#[tokio::test]
+async fn range() {
+    let uri = get_mongodb_uri("test_range");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    let image_small = fs::read(IMAGE_SMALL_PATH).await.unwrap();
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    {
+        let file_id =
+            file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+
+        let data = file_center
+            .get_file_range_by_id(file_id, 100..2000)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(&image_small[100..2000], data.as_slice());
+
+        let data = file_center
+            .get_file_range_by_id(file_id, 11000..)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(&image_small[11000..], data.as_slice());
+    }
+
+    {
+        let file_id =
+            file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+        let data = file_center
+            .get_file_range_by_id(file_id, 300000..=900000)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(&image_big[300000..=900000], data.as_slice());
+
+        let data = file_center
+            .get_file_range_by_id(file_id, ..10)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(&image_big[..10], data.as_slice());
+
+        let data = file_center
+            .get_file_range_by_id(file_id, 1300000..IMAGE_BIG_SIZE * 2)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(&image_big[1300000..], data.as_slice());
+
+        let data = file_center
+            .get_file_range_by_id(file_id, IMAGE_BIG_SIZE..)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap();
+
+        assert!(data.is_empty());
+    }
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{bson::doc, mime, FileCenter, FileQuery, StreamExt, UploadOptions};
+use tokio::fs;
+
+#[tokio::test]
+async fn reference() {
+    let uri = get_mongodb_uri("test_reference");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    let file_id_1 = file_center
+        .put_file_by_buffer(image_big.clone(), "invoice.pdf", Some(mime::APPLICATION_PDF))
+        .await
+        .unwrap();
+
+    let file_id_2 = file_center
+        .put_file_by_path_with_options(IMAGE_BIG_PATH, Some("receipt.jpg"), None, UploadOptions {
+            metadata: Some(doc! {
+                "owner": "bob"
+            }),
+            ..UploadOptions::default()
+        })
+        .await
+        .unwrap();
+
+    assert_ne!(file_id_1, file_id_2);
+
+    {
+        let file_info_1 = file_center.get_file_info_by_id(file_id_1).await.unwrap().unwrap();
+        let file_info_2 = file_center.get_file_info_by_id(file_id_2).await.unwrap().unwrap();
+
+        assert_eq!("invoice.pdf", file_info_1.get_file_name());
+        assert_eq!(&mime::APPLICATION_PDF, file_info_1.get_mime_type());
+        assert!(file_info_1.get_metadata().is_none());
+
+        assert_eq!("receipt.jpg", file_info_2.get_file_name());
+        assert_eq!(&mime::IMAGE_JPEG, file_info_2.get_mime_type());
+        assert_eq!(IMAGE_BIG_SIZE, file_info_2.get_file_size());
+        assert_eq!(
+            Some(&doc! {
+                "owner": "bob"
+            }),
+            file_info_2.get_metadata()
+        );
+
+        assert_eq!(2, file_info_1.get_count());
+        assert_eq!(2, file_info_2.get_count());
+
+        let counts = file_center
+            .find_file_infos(FileQuery::default())
+            .await
+            .unwrap()
+            .map(|info| info.unwrap().get_count())
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(vec![2, 2], counts);
+    }
+
+    assert!(file_center.delete_file_item_by_id(file_id_1).await.unwrap().is_some());
+    assert!(file_center.get_file_item_by_id(file_id_1).await.unwrap().is_none());
+
+    {
+        let file_item = file_center.get_file_item_by_id(file_id_2).await.unwrap().unwrap();
+
+        assert_eq!("receipt.jpg", file_item.get_file_name());
+        assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+        let data = file_center
+            .get_file_range_by_id(file_id_2, 1000..2000)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(&image_big[1000..2000], data.as_slice());
+    }
+
+    let file_id_3 = file_center.put_file_by_buffer(image_big.clone(), "", None).await.unwrap();
+
+    assert_eq!(2, file_center.get_file_info_by_id(file_id_3).await.unwrap().unwrap().get_count());
+
+    assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_some());
+    assert!(file_center.delete_file_item_by_id(file_id_3).await.unwrap().is_some());
+
+    assert!(file_center
+        .find_file_infos(FileQuery::default())
+        .await
+        .unwrap()
+        .next()
+        .await
+        .is_none());
+
+    file_center.drop_database().await.unwrap();
+}
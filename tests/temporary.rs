@@ -1,7 +1,11 @@
 mod common;
 
+use std::time::Duration;
+
 use common::*;
-use mongo_file_center::FileCenter;
+use mongo_file_center::{
+    bson::DateTime, Expiration, FileCenter, ReadLimit, UploadOptions, DEFAULT_TEMPORARY_LIFE_TIME,
+};
 use tokio::fs::{self, File};
 
 #[tokio::test]
@@ -141,3 +145,138 @@ async fn temporary() {
 
     file_center.drop_database().await.unwrap();
 }
+
+#[tokio::test]
+async fn temporary_life_time() {
+    let uri = get_mongodb_uri("test_temporary_life_time");
+
+    let mut file_center = FileCenter::new(&uri).await.unwrap();
+
+    assert_eq!(DEFAULT_TEMPORARY_LIFE_TIME, file_center.get_temporary_life_time());
+
+    let one_day = Duration::from_secs(86400);
+
+    file_center.set_temporary_life_time(one_day).await.unwrap();
+
+    {
+        let file_center = FileCenter::new(&uri).await.unwrap();
+
+        assert_eq!(one_day, file_center.get_temporary_life_time());
+    }
+
+    {
+        let file_id = file_center
+            .put_file_by_path_temporarily(IMAGE_SMALL_PATH, None::<&str>, None)
+            .await
+            .unwrap();
+
+        let file_info = file_center.get_file_info_by_id(file_id).await.unwrap().unwrap();
+
+        let life_time = file_info.get_expiration_time().unwrap().timestamp_millis()
+            - file_info.get_create_time().timestamp_millis();
+
+        assert_eq!(one_day.as_millis() as i64, life_time);
+    }
+
+    {
+        let file_id = file_center
+            .put_file_by_buffer_temporarily_with_options(
+                fs::read(IMAGE_BIG_PATH).await.unwrap(),
+                "",
+                None,
+                UploadOptions {
+                    expiration: Some(Expiration::After(Duration::from_secs(10))),
+                    ..UploadOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let file_info = file_center.get_file_info_by_id(file_id).await.unwrap().unwrap();
+
+        let life_time = file_info.get_expiration_time().unwrap().timestamp_millis()
+            - file_info.get_create_time().timestamp_millis();
+
+        assert_eq!(10000, life_time);
+    }
+
+    {
+        let file_id = file_center
+            .put_file_by_path_temporarily_with_options(
+                IMAGE_BIG_PATH,
+                None::<&str>,
+                None,
+                UploadOptions {
+                    expiration: Some(Expiration::After(Duration::MAX)),
+                    ..UploadOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let file_info = file_center.get_file_info_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(i64::MAX, file_info.get_expiration_time().unwrap().timestamp_millis());
+    }
+
+    assert!(file_center
+        .put_file_by_reader_temporarily_with_options(
+            File::open(IMAGE_SMALL_PATH).await.unwrap(),
+            "",
+            None,
+            UploadOptions {
+                expiration: Some(Expiration::At(DateTime::from_millis(0))),
+                ..UploadOptions::default()
+            },
+        )
+        .await
+        .is_err());
+
+    assert!(file_center.set_temporary_life_time(Duration::ZERO).await.is_err());
+
+    file_center.drop_database().await.unwrap();
+}
+
+#[tokio::test]
+async fn temporary_read_limit() {
+    let uri = get_mongodb_uri("test_temporary_read_limit");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    {
+        let file_id = file_center
+            .put_file_by_path_temporarily_with_options(
+                IMAGE_BIG_PATH,
+                None::<&str>,
+                None,
+                UploadOptions {
+                    read_limit: Some(ReadLimit::Limited(2)),
+                    ..UploadOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(file_center.get_file_item_by_id(file_id).await.unwrap().is_some());
+        assert!(file_center.get_file_range_by_id(file_id, ..100).await.unwrap().is_some());
+        assert!(file_center.get_file_item_by_id(file_id).await.unwrap().is_none());
+    }
+
+    {
+        let file_id = file_center
+            .put_file_by_buffer_temporarily_with_options(b"Hello", "", None, UploadOptions {
+                read_limit: Some(ReadLimit::Unlimited),
+                ..UploadOptions::default()
+            })
+            .await
+            .unwrap();
+
+        for _ in 0..5 {
+            let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+            assert_eq!(b"Hello".to_vec(), file_item.into_file_data().into_vec().await.unwrap());
+        }
+    }
+
+    file_center.drop_database().await.unwrap();
+}
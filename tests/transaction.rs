@@ -0,0 +1,85 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{FileCenter, FileCenterError, UploadOptions};
+use tokio::{
+    fs::{self, File},
+    io,
+};
+
+#[tokio::test]
+async fn transaction() {
+    let uri = get_mongodb_uri("test_transaction");
+
+    let mut file_center = FileCenter::new(uri).await.unwrap();
+
+    match file_center.set_transactions_enabled(true).await {
+        Ok(()) => (),
+        Err(FileCenterError::TransactionsNotSupportedError) => {
+            // a standalone server cannot run transactions
+            file_center.drop_database().await.unwrap();
+
+            return;
+        },
+        Err(err) => panic!("{}", err),
+    }
+
+    assert!(file_center.is_transactions_enabled());
+
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    let file_id_1 = file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+    let file_id_2 = file_center
+        .put_file_by_reader(File::open(IMAGE_BIG_PATH).await.unwrap(), "", None)
+        .await
+        .unwrap();
+
+    assert_eq!(2, file_center.get_file_info_by_id(file_id_1).await.unwrap().unwrap().get_count());
+
+    // concurrent uploads of the same content conflict with each other, and are retried
+    let (result_1, result_2) = tokio::join!(
+        file_center.put_file_by_buffer(image_big.as_slice(), "", None),
+        file_center.put_file_by_buffer(image_big.as_slice(), "", None)
+    );
+
+    file_center.delete_file_item_by_id(result_1.unwrap()).await.unwrap();
+    file_center.delete_file_item_by_id(result_2.unwrap()).await.unwrap();
+
+    // a temporary file which has the same content is persisted as a reference
+    let file_id_4 =
+        file_center.put_file_by_path_temporarily(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+    assert!(file_center.persist_temporary_file(file_id_4).await.unwrap());
+
+    assert_eq!(3, file_center.get_file_info_by_id(file_id_1).await.unwrap().unwrap().get_count());
+    assert_eq!(Some(IMAGE_BIG_SIZE), file_center.delete_file_item_by_id(file_id_4).await.unwrap());
+
+    let mut upload_stream = file_center
+        .open_upload_stream_temporarily("", None, UploadOptions::default())
+        .await
+        .unwrap();
+
+    io::copy(&mut File::open(IMAGE_BIG_PATH).await.unwrap(), &mut upload_stream).await.unwrap();
+
+    let file_id_3 = upload_stream.finish().await.unwrap();
+
+    assert_eq!(
+        image_big,
+        file_center
+            .get_file_item_by_id(file_id_3)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_file_data()
+            .into_vec()
+            .await
+            .unwrap()
+    );
+
+    assert_eq!(Some(IMAGE_BIG_SIZE), file_center.delete_file_item_by_id(file_id_1).await.unwrap());
+    assert_eq!(Some(IMAGE_BIG_SIZE), file_center.delete_file_item_by_id(file_id_2).await.unwrap());
+    assert!(file_center.delete_file_item_by_id(file_id_2).await.unwrap().is_none());
+
+    file_center.drop_database().await.unwrap();
+}
@@ -262,3 +262,25 @@ async fn upload_download_data() {
 
     file_center.drop_database().await.unwrap();
 }
+
+#[tokio::test]
+async fn download_async_read() {
+    let uri = get_mongodb_uri("test_download_async_read");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    for path in [IMAGE_SMALL_PATH, IMAGE_BIG_PATH] {
+        let file_id = file_center.put_file_by_path(path, None::<&str>, None).await.unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert!(same_content::same_content_from_readers_async(
+            &mut file_item.into_file_data().into_async_read(),
+            &mut File::open(path).await.unwrap()
+        )
+        .await
+        .unwrap());
+    }
+
+    file_center.drop_database().await.unwrap();
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{FileCenter, FileData, UploadOptions};
+use tokio::{
+    fs::{self, File},
+    io::{self, AsyncWriteExt},
+};
+
+#[tokio::test]
+async fn upload_stream() {
+    let uri = get_mongodb_uri("test_upload_stream");
+
+    let file_center = FileCenter::new(uri).await.unwrap();
+
+    let image_small = fs::read(IMAGE_SMALL_PATH).await.unwrap();
+    let image_big = fs::read(IMAGE_BIG_PATH).await.unwrap();
+
+    {
+        let mut upload_stream = file_center
+            .open_upload_stream("image.jpg", None, UploadOptions::default())
+            .await
+            .unwrap();
+
+        io::copy(&mut File::open(IMAGE_BIG_PATH).await.unwrap(), &mut upload_stream).await.unwrap();
+
+        let file_id = upload_stream.finish().await.unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(IMAGE_BIG_SIZE, file_item.get_file_size());
+        assert_eq!("image.jpg", file_item.get_file_name());
+        assert_eq!(image_big, file_item.into_file_data().into_vec().await.unwrap());
+
+        let file_id_2 = file_center.put_file_by_buffer(image_big.clone(), "", None).await.unwrap();
+
+        assert_eq!(
+            2,
+            file_center.get_file_info_by_id(file_id_2).await.unwrap().unwrap().get_count()
+        );
+    }
+
+    {
+        let mut upload_stream = file_center
+            .open_upload_stream_temporarily("", None, UploadOptions::default())
+            .await
+            .unwrap();
+
+        upload_stream.write_all(&image_small).await.unwrap();
+
+        let file_id = upload_stream.finish().await.unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert!(file_item.get_expiration_time().is_some());
+
+        match file_item.into_file_data() {
+            FileData::Buffer(b) => assert_eq!(image_small, b),
+            FileData::Stream(_) => panic!("should be a buffer"),
+        }
+
+        assert!(file_center.get_file_item_by_id(file_id).await.unwrap().is_none());
+    }
+
+    {
+        let upload_stream =
+            file_center.open_upload_stream("", None, UploadOptions::default()).await.unwrap();
+
+        let file_id = upload_stream.finish().await.unwrap();
+
+        let file_item = file_center.get_file_item_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(0, file_item.get_file_size());
+    }
+
+    file_center.drop_database().await.unwrap();
+}
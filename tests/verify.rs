@@ -0,0 +1,169 @@
+mod common;
+
+use common::*;
+use mongo_file_center::{
+    bson::{doc, Document},
+    mongodb::Client,
+    FileCenter, VerificationIssue, VerifyOptions, COLLECTION_FILES_CHUNKS_NAME,
+    COLLECTION_FILES_NAME,
+};
+
+#[tokio::test]
+async fn verify() {
+    let uri = get_mongodb_uri("test_verify");
+
+    let file_center = FileCenter::new(&uri).await.unwrap();
+
+    let db = Client::with_uri_str(&uri).await.unwrap().database("test_verify");
+    let collection_files = db.collection::<Document>(COLLECTION_FILES_NAME);
+    let collection_files_chunks = db.collection::<Document>(COLLECTION_FILES_CHUNKS_NAME);
+
+    let file_id_1 =
+        file_center.put_file_by_path(IMAGE_SMALL_PATH, None::<&str>, None).await.unwrap();
+    let file_id_2 = file_center.put_file_by_path(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+    let file_id_3 =
+        file_center.put_file_by_path_temporarily(IMAGE_BIG_PATH, None::<&str>, None).await.unwrap();
+
+    let report = file_center.verify(VerifyOptions::default()).await.unwrap();
+
+    assert_eq!(3, report.checked_items);
+    assert!(report.is_ok());
+
+    // break the file center
+    collection_files
+        .update_one(
+            doc! {
+                "_id": file_id_1
+            },
+            doc! {
+                "$set": {
+                    "count": -1
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    collection_files_chunks
+        .delete_one(
+            doc! {
+                "file_id": file_id_2,
+                "n": 1i64
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    collection_files
+        .update_one(
+            doc! {
+                "_id": file_id_3
+            },
+            doc! {
+                "$unset": {
+                    "chunk_id": ""
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let report = file_center.verify(VerifyOptions::default()).await.unwrap();
+
+    assert_eq!(3, report.issues.len());
+    assert!(report
+        .issues
+        .contains(&VerificationIssue::NegativeCount {
+            file_id: file_id_1, count: -1
+        }));
+    assert!(report
+        .issues
+        .contains(&VerificationIssue::ChunkGap {
+            file_id: file_id_2, missing: vec![1]
+        }));
+    assert!(report.issues.contains(&VerificationIssue::MissingData {
+        file_id: file_id_3
+    }));
+
+    // repair it
+    let report = file_center
+        .verify(VerifyOptions {
+            check_hashes:        false,
+            repair_counts:       true,
+            remove_broken_items: true,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(1, report.repaired_items);
+    assert_eq!(2, report.removed_items);
+
+    let report = file_center.verify(VerifyOptions::default()).await.unwrap();
+
+    assert_eq!(1, report.checked_items);
+    assert!(report.is_ok());
+
+    assert_eq!(1, file_center.get_file_info_by_id(file_id_1).await.unwrap().unwrap().get_count());
+
+    // malformed documents are reported, and the files which are referred to are kept
+    let file_id_4 = file_center.put_file_by_buffer(b"Hello", "", None).await.unwrap();
+    let file_id_5 = file_center.put_file_by_buffer(b"Hello", "", None).await.unwrap();
+    let file_id_6 = file_center.put_file_by_buffer(b"World", "", None).await.unwrap();
+
+    collection_files
+        .update_one(
+            doc! {
+                "_id": file_id_4
+            },
+            doc! {
+                "$set": {
+                    "file_data": "Hello"
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    collection_files
+        .update_one(
+            doc! {
+                "_id": file_id_6
+            },
+            doc! {
+                "$set": {
+                    "file_size": "5"
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let report = file_center
+        .verify(VerifyOptions {
+            remove_broken_items: true,
+            ..VerifyOptions::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(4, report.checked_items);
+    assert_eq!(2, report.issues.len());
+    assert!(report.issues.contains(&VerificationIssue::CorruptedData {
+        file_id: file_id_4
+    }));
+    assert!(report.issues.contains(&VerificationIssue::MalformedDocument {
+        file_id: file_id_6
+    }));
+    assert_eq!(1, report.removed_items);
+
+    assert!(file_center.check_file_item_exist(file_id_4).await.unwrap());
+    assert!(file_center.check_file_item_exist(file_id_5).await.unwrap());
+    assert!(!file_center.check_file_item_exist(file_id_6).await.unwrap());
+
+    file_center.drop_database().await.unwrap();
+}